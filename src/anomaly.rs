@@ -1,31 +1,78 @@
 use std::collections::{HashMap, HashSet};
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 use std::thread;
 
-use crate::f32_3::dd_f32_3;
-use crate::f64_3::mltply_f64_3;
-use crate::magma_ocean::{magma, petrify, Stone};
+use crate::arena::{establish, insert_by_id, remove_by_id, Id, Ledger};
+use crate::columns::{central_law, central_laws, gather, scatter, step_columns, Central, Columns};
+use crate::conservation::{begin, end, Validator};
+use crate::constraint::{constraint_forces, constraints_of, rattle, shake, Constraint};
+use crate::decay::{decay_step, species_named, Decay, SPECIES};
+use crate::distribution::{Distribution, Particular};
+use crate::ewald::{wrap, Ewald};
+use crate::expression::Expression;
+use crate::f64_3::{dd_f64_3, mltply_f64_3, nrmlz_f64_3, sbtr_f64_3, vector_length};
+use crate::field::{field_act, Field};
+use crate::force::{central_apply, expression_apply, force_laws};
+use crate::magma_ocean::{magma, magma_oriented, petrify, Stone};
+use crate::nuclear::{nuclear_interact, Yukawa};
 use crate::positions::move_positions;
+use crate::query::visit_anomalies_mut;
+use crate::radiation::{charged_velocities, radiate, Radiation};
+use crate::reaction::{reaction_step, Reaction};
+use crate::rigid::{rigid_columns, Rigid};
+use crate::spin::{spin_axis, spin_interact};
+use crate::thermostat::{thermostat_step, Thermostat};
 use crate::u_modular::modular_offset_in_range;
+use crate::wavepacket::{age_wavepackets, wavepacket_view, wavepacket_width};
 
 pub static TS_F64: f64 = 5.391247 * 1e-44;
 pub static LS_F64: f64 = 299792458.0 * 1000000000.0 * 6.1879273537329 * 1e+25;
+pub static ML_F64: f64 = 1000000000.0 * 6.1879273537329 * 1e+25;
+pub static EP_F64: f64 = 1.220890 * 1e+22;
+pub static HB_F64: f64 = 6.582119569 * 1e-22;
+pub static AF_F64: f64 = 1.0 / 137.0;
 
 pub struct Anomaly {
     pub anomaly: Vec<Anomaly>,
     pub component: Vec<Component>,
     pub force: Vec<Force>,
+    pub field: Vec<Field>,
+    pub clock: f64,
+    pub id: Id,
+    pub ledger: Vec<Ledger>,
+    // empty unless the composite moves as one rigid body, see rigid.rs
+    pub rigid: Vec<Rigid>,
+    // ties between components below, see constraint.rs
+    pub constraint: Vec<Constraint>,
+    // the residual strong force the composite feels, see nuclear.rs
+    pub yukawa: Vec<Yukawa>,
+    // holds the columns below at a temperature, see thermostat.rs
+    pub thermostat: Vec<Thermostat>,
+    // larmor losses of the charges below, see radiation.rs
+    pub radiation: Vec<Radiation>,
+    // decays of the unstable particles below, see decay.rs
+    pub decay: Vec<Decay>,
+    // collisions of the particles below, see reaction.rs
+    pub reaction: Vec<Reaction>,
+    // checks every change below keeps the quantum numbers, see conservation.rs
+    pub validator: Vec<Validator>,
+    // the box the anomaly repeats in, its sums set up once, see ewald.rs
+    pub periodic: Vec<Arc<Ewald>>,
+    // lines of what watches the anomaly from outside, not yet taken into a snapshot
+    pub log: Vec<String>,
 }
 
 pub struct Composition {
-    pub space: Vec<[f32; 3]>,
-    pub distribution: Vec<fn(Vec<[f32; 3]>) -> Vec<[f32; 3]>>,
+    // world positions in planck lengths, made f32 only relative to a view origin
+    pub space: Vec<[f64; 3]>,
+    pub distribution: Vec<Box<dyn Distribution>>,
 }
 
 pub struct Component {
     pub component: Vec<Component>,
     pub composition: Vec<Composition>,
     pub property: Vec<Property>,
+    pub id: Id,
 }
 
 pub struct Property {
@@ -33,17 +80,20 @@ pub struct Property {
     pub value: f64,
 }
 
+// domain, range and nesting are read as described in force.rs
 pub struct Force {
     pub force: Vec<Force>,
     pub range: Vec<f64>,
     pub domain: Vec<Component>,
+    pub law: Vec<Arc<Expression>>,
 }
 
-pub fn interact(anom: &mut Anomaly) {
+// laws are the central laws of the root, which the columns step
+pub fn interact(anom: &mut Anomaly, laws: &Vec<Central>, time: f64) {
     thread::scope(|s| {
         let mut handles: Vec<thread::ScopedJoinHandle<()>> = vec![];
         for mut a in anom.anomaly.iter_mut() {
-            let handle = s.spawn(move || interact(&mut a));
+            let handle = s.spawn(move || interact(&mut a, laws, time));
             handles.push(handle);
         }
         for h in handles {
@@ -94,7 +144,7 @@ pub fn interact(anom: &mut Anomaly) {
                     let handle = s.spawn(move || {
                         let mut a = rx.recv().unwrap();
                         let mut b = rx.recv().unwrap();
-                        anomaly_2_interact(&mut a, &mut b);
+                        anomaly_2_interact(&mut a, &mut b, laws, time);
                     });
                     handles.push(handle);
                 }
@@ -118,7 +168,7 @@ pub fn interact(anom: &mut Anomaly) {
         }
     }
 
-    component_interact(anom);
+    component_interact(anom, laws, time);
 }
 
 //fn iter_chunks<T, const CHUNK_SIZE: usize>(
@@ -139,64 +189,154 @@ fn array_collect<T, const N: usize>(mut iter: impl Iterator<Item = T>) -> [T; N]
     a.map(|_| iter.next().unwrap())
 }
 
-pub fn anomaly_2_interact(a: &mut Anomaly, b: &mut Anomaly) {
+pub fn anomaly_2_interact(a: &mut Anomaly, b: &mut Anomaly, laws: &Vec<Central>, time: f64) {
     for i in a.anomaly.iter_mut() {
         for j in b.anomaly.iter_mut() {
-            anomaly_2_interact(i, j);
+            anomaly_2_interact(i, j, laws, time);
         }
     }
 
+    nuclear_interact(a, b, time);
+
     for df in &a.force {
         for i in 0..a.component.len() {
             for j in 0..b.component.len() {
-                component_2_interact(df, &mut a.component[i], &mut b.component[j]);
+                component_2_interact(df, &mut a.component[i], &mut b.component[j], laws, time);
             }
         }
     }
 }
 
-pub fn component_interact(_anom: &mut Anomaly) {
+pub fn component_interact(_anom: &mut Anomaly, laws: &Vec<Central>, time: f64) {
+    // force_apply acts on both components, so every unordered pair is visited once
     for df in &_anom.force {
         for i in 0.._anom.component.len() {
-            for j in 0.._anom.component.len() {
-                let (e, f) = if i < j {
-                    let (left, right) = _anom.component.split_at_mut(j);
-                    (&mut left[i], &mut right[0])
-                } else if i == j {
-                    continue;
-                } else {
-                    let (left, right) = _anom.component.split_at_mut(i);
-                    (&mut right[0], &mut left[j])
-                };
-                component_2_interact(df, e, f);
+            for j in i + 1.._anom.component.len() {
+                let (left, right) = _anom.component.split_at_mut(j);
+                component_2_interact(df, &mut left[i], &mut right[0], laws, time);
             }
         }
     }
 }
 
-pub fn component_2_interact(df: &Force, a: &mut Component, b: &mut Component) {
+pub fn component_2_interact(
+    df: &Force,
+    a: &mut Component,
+    b: &mut Component,
+    laws: &Vec<Central>,
+    time: f64,
+) {
     for i in a.component.iter_mut() {
         for j in b.component.iter_mut() {
-            component_2_interact(df, i, j);
+            component_2_interact(df, i, j, laws, time);
         }
     }
 
-    force_apply(df, a, b);
+    force_apply(df, a, b, laws, time);
 }
 
-pub fn force_apply(_f: &Force, a: &mut Component, b: &mut Component) {
-    // set_inertia([0.0, 0.0, 0.0], a);
+// applies every law the pair falls under, see force.rs for the rules; a
+// central law the root already steps in columns is left to them
+pub fn force_apply(
+    f: &Force,
+    a: &mut Component,
+    b: &mut Component,
+    laws: &Vec<Central>,
+    time: f64,
+) {
+    if central_law(f).is_some_and(|l| laws.contains(&l)) {
+        return;
+    }
+    let distance = vector_length(sbtr_f64_3(component_position(b), component_position(a)));
 
-    // set_inertia([0.0, 0.0, 0.0], b);
+    for law in force_laws(f, a, b, distance) {
+        if let Some(e) = law.expression {
+            expression_apply(&law, e, a, b, time);
+            continue;
+        }
+        if law.name == SP {
+            spin_interact(law.coupling, a, b, time);
+        }
+        if law.name == EC || law.name == MS {
+            central_apply(&law, a, b, time);
+        }
+    }
 }
 
 pub fn progress(anom: &mut Anomaly, time: f64) {
+    progress_by(anom, time, &mut step_columns);
+}
+
+// progress with the column stepping handed in, so it can run somewhere else
+pub fn progress_by(
+    anom: &mut Anomaly,
+    time: f64,
+    step: &mut impl FnMut(&mut Columns, &Vec<Central>, f64),
+) {
+    // radiation needs the velocities from before anything acted
+    let velocity = if anom.radiation.is_empty() {
+        HashMap::new()
+    } else {
+        charged_velocities(anom)
+    };
+
+    // every moving component below is stepped together in columns, under the
+    // central laws of the root; the tree applies the rest
+    let laws = central_laws(&anom.force);
+    progress_interactions(anom, &laws, time);
+
+    visit_anomalies_mut(anom, &mut |_, a| field_act(a, time));
+
+    let constraint = constraints_of(anom);
+    let mut columns = gather(anom);
+    let before: Vec<[f64; 3]> = (0..columns.len()).map(|k| columns.position(k)).collect();
+    constraint_forces(&constraint, &mut columns, time);
+    step(&mut columns, &laws, time);
+    let mut photon = vec![];
+    for r in anom.radiation.iter_mut() {
+        photon.extend(radiate(r, &velocity, &mut columns, time));
+    }
+    for t in anom.thermostat.iter_mut() {
+        thermostat_step(t, &mut columns, time);
+    }
+    shake(&constraint, &before, &mut columns, time);
+    rattle(&constraint, &mut columns);
+    rigid_columns(anom, &mut columns, time);
+    if let Some(size) = anom.periodic.first().map(|p| p.size) {
+        wrap(anom, &constraint, &mut columns, size);
+    }
+    scatter(&columns, anom);
+    if !photon.is_empty() {
+        begin(anom);
+        let root = anom.id;
+        for p in photon {
+            add_particle_by(anom, root, p);
+        }
+        end(anom, "radiation");
+    }
+    let mut decay = std::mem::take(&mut anom.decay);
+    for d in decay.iter_mut() {
+        decay_step(d, anom, time);
+    }
+    anom.decay = decay;
+    let mut reaction = std::mem::take(&mut anom.reaction);
+    for r in reaction.iter_mut() {
+        reaction_step(r, anom);
+    }
+    anom.reaction = reaction;
+
+    age_wavepackets(anom, time);
+    visit_anomalies_mut(anom, &mut |_, a| a.clock += time);
+}
+
+// the interactions of progress at every level, the stepping is left to the columns
+fn progress_interactions(anom: &mut Anomaly, laws: &Vec<Central>, time: f64) {
     thread::scope(|s| {
         let mut handles: Vec<thread::ScopedJoinHandle<()>> = vec![];
         for mut a in anom.anomaly.iter_mut() {
             let handle = s.spawn(move || {
-                interact(&mut a);
-                progress(&mut a, time);
+                interact(&mut a, laws, time);
+                progress_interactions(&mut a, laws, time);
             });
             handles.push(handle);
         }
@@ -206,47 +346,27 @@ pub fn progress(anom: &mut Anomaly, time: f64) {
     });
 
     for i in 0..anom.anomaly.len() {
-        for j in 0..anom.anomaly.len() {
-            let (e, f) = if i < j {
-                let (left, right) = anom.anomaly.split_at_mut(j);
-                (&mut left[i], &mut right[0])
-            } else if i == j {
-                continue;
-            } else {
-                let (left, right) = anom.anomaly.split_at_mut(i);
-                (&mut right[0], &mut left[j])
-            };
-            anomaly_2_interact(e, f);
+        for j in i + 1..anom.anomaly.len() {
+            let (left, right) = anom.anomaly.split_at_mut(j);
+            anomaly_2_interact(&mut left[i], &mut right[0], laws, time);
         }
     }
-
-    let steps = (time / TS_F64) as u64;
-    for _ in 0..steps {
-        thread::scope(|s| {
-            let mut handles: Vec<thread::ScopedJoinHandle<()>> = vec![];
-            for mut c in anom.component.iter_mut() {
-                let handle = s.spawn(move || {
-                    component_progress(&mut c, TS_F64);
-                });
-                handles.push(handle);
-            }
-            for h in handles {
-                h.join().unwrap();
-            }
-        });
-    }
 }
 
-pub fn component_property(component: &mut Component, name: f64) -> f64 {
-    let prop: Vec<&mut Property> = component
+pub fn component_property(component: &Component, name: f64) -> f64 {
+    let prop: Vec<&Property> = component
         .property
-        .iter_mut()
+        .iter()
         .filter(|c| c.name == name)
         .collect();
 
     return prop[0].value;
 }
 
+pub fn has_component_property(component: &Component, name: f64) -> bool {
+    return component.property.iter().any(|c| c.name == name);
+}
+
 pub fn set_component_property(n: f64, s: f64, component: &mut Component) {
     for p in component.property.iter_mut() {
         if n == p.name {
@@ -261,24 +381,51 @@ pub fn set_inertia(in0: [f64; 3], c: &mut Component) {
     set_component_property(IN2, in0[2], c);
 }
 
-pub fn component_progress(component: &mut Component, time: f64) {
-    for mut c in component.component.iter_mut() {
-        component_progress(&mut c, time);
+pub fn component_inertia(c: &Component) -> [f64; 3] {
+    return [
+        component_property(c, IN0),
+        component_property(c, IN1),
+        component_property(c, IN2),
+    ];
+}
+
+// the center of every point the component occupies
+pub fn component_position(c: &Component) -> [f64; 3] {
+    let mut sum = [0.0, 0.0, 0.0];
+    let mut count = 0;
+    for k in &c.composition {
+        for s in &k.space {
+            sum = dd_f64_3(sum, *s);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return sum;
     }
 
-    let inertia_0 = component_property(component, IN0);
-    let inertia_1 = component_property(component, IN1);
-    let inertia_2 = component_property(component, IN2);
+    return mltply_f64_3(sum, 1.0 / count as f64);
+}
 
-    for c in &mut component.composition {
-        for s in c.space.iter_mut() {
-            let mov0 = mltply_f64_3([inertia_0, inertia_1, inertia_2], TS_F64);
-            *s = dd_f32_3(*s, [mov0[0] as f32, mov0[1] as f32, mov0[2] as f32]);
-        }
+// force in MeV per planck length, inertia kept below light speed
+pub fn accelerate(force: [f64; 3], time: f64, c: &mut Component) {
+    if !has_component_property(c, MS) || !has_component_property(c, IN0) {
+        return;
+    }
+    let mass = component_property(c, MS);
+    if mass <= 0.0 {
+        return;
     }
+
+    let dv = mltply_f64_3(force, LS_F64 * LS_F64 * time / mass);
+    let mut inertia = dd_f64_3(component_inertia(c), dv);
+    if vector_length(inertia) > LS_F64 {
+        inertia = mltply_f64_3(nrmlz_f64_3(inertia), LS_F64);
+    }
+    set_inertia(inertia, c);
 }
 
-pub fn view(anom: &mut Anomaly) -> Vec<Stone> {
+// stones placed relative to the origin, so they are accurate near it in f32
+pub fn view(anom: &mut Anomaly, origin: [f64; 3]) -> Vec<Stone> {
     let mut ret: Vec<Stone> = vec![];
     let mut rs: Vec<mpsc::Receiver<Vec<Stone>>> = vec![];
 
@@ -287,14 +434,14 @@ pub fn view(anom: &mut Anomaly) -> Vec<Stone> {
             let (tx, rx) = mpsc::channel();
             rs.push(rx);
             s.spawn(move || {
-                let k = view(&mut a);
+                let k = view(&mut a, origin);
                 tx.send(k).unwrap();
             });
         }
     });
 
     for c in anom.component.iter_mut() {
-        ret.append(&mut component_view(c));
+        ret.append(&mut component_view(c, origin));
     }
 
     for r in rs {
@@ -305,20 +452,38 @@ pub fn view(anom: &mut Anomaly) -> Vec<Stone> {
     ret
 }
 
-pub fn component_view(component: &mut Component) -> Vec<Stone> {
+pub fn component_view(component: &mut Component, origin: [f64; 3]) -> Vec<Stone> {
     let mut ret: Vec<Stone> = vec![];
 
     for c in component.component.iter_mut() {
-        ret.append(&mut component_view(c));
+        ret.append(&mut component_view(c, origin));
     }
 
     let size = component_property(component, MS);
+    let axis = spin_axis(component);
+
+    let packet = wavepacket_width(component).is_some();
 
     for c in &component.composition {
         for d in &c.distribution {
-            for v in &d(c.space.clone()) {
-                let mut s = petrify(magma(2, size as f32));
-                move_positions(&mut s.positions, *v);
+            for w in &d.distribute(&c.space) {
+                let v = [
+                    (w[0] - origin[0]) as f32,
+                    (w[1] - origin[1]) as f32,
+                    (w[2] - origin[2]) as f32,
+                ];
+                if packet {
+                    ret.append(&mut wavepacket_view(component, v, size as f32));
+                    continue;
+                }
+                let mut s = match axis {
+                    Some(a) => petrify(magma_oriented(
+                        [a[0] as f32, a[1] as f32, a[2] as f32],
+                        size as f32,
+                    )),
+                    None => petrify(magma(2, size as f32)),
+                };
+                move_positions(&mut s.positions, v);
                 ret.push(s);
             }
         }
@@ -326,37 +491,91 @@ pub fn component_view(component: &mut Component) -> Vec<Stone> {
     ret
 }
 
-pub fn add_particle_by(anom: &mut Anomaly, p: Anomaly) {
-    anom.anomaly.push(p);
+pub fn vacuum() -> Anomaly {
+    return Anomaly {
+        anomaly: vec![],
+        component: vec![],
+        force: vec![],
+        field: vec![],
+        clock: 0.0,
+        id: Id::default(),
+        ledger: vec![],
+        rigid: vec![],
+        constraint: vec![],
+        yukawa: vec![],
+        thermostat: vec![],
+        radiation: vec![],
+        decay: vec![],
+        reaction: vec![],
+        validator: vec![],
+        periodic: vec![],
+        log: vec![],
+    };
+}
+
+// registers the particle in the root's ledger and puts it below the parent,
+// however deep that is; a root without a ledger is given one first
+pub fn add_particle_by(root: &mut Anomaly, parent: Id, p: Anomaly) -> Option<Id> {
+    let parent = if root.ledger.is_empty() && parent == root.id {
+        establish(root);
+        root.id
+    } else {
+        parent
+    };
+    return insert_by_id(root, parent, p);
+}
+
+pub fn remove_particle_by(root: &mut Anomaly, id: Id) -> Option<Anomaly> {
+    return remove_by_id(root, id);
+}
+
+pub fn particle(position: [f64; 3], properties: Vec<Property>) -> Anomaly {
+    return extended_particle(position, properties, Box::new(Particular {}));
 }
 
-pub fn particle(position: [f32; 3], properties: Vec<Property>) -> Anomaly {
+// a single component spread over the points of its distribution
+pub fn extended_particle(
+    position: [f64; 3],
+    properties: Vec<Property>,
+    distribution: Box<dyn Distribution>,
+) -> Anomaly {
     let anom = Anomaly {
-        anomaly: vec![],
         component: vec![Component {
             component: vec![],
             composition: vec![Composition {
                 space: vec![position],
-                distribution: vec![particular],
+                distribution: vec![distribution],
             }],
             property: properties,
+            id: Id::default(),
         }],
         force: force_base().force,
+        ..vacuum()
     };
 
     anom
 }
 
-static EC: f64 = 313.0;
-static SP: f64 = 591.0;
-static MS: f64 = 343.0;
-static CR: f64 = 0.10;
-static IN0: f64 = 141.0;
-static IN1: f64 = 141.1;
-static IN2: f64 = 141.2;
-static QMS: [f64; 6] = [2.2, 4.7, 1.28, 96.0, 173.1, 4.18];
-
-pub fn e(position: [f32; 3], inertia: [f64; 3], clock: bool) -> Anomaly {
+pub static EC: f64 = 313.0;
+pub static SP: f64 = 591.0;
+pub static MS: f64 = 343.0;
+pub static CR: f64 = 0.10;
+pub static IN0: f64 = 141.0;
+pub static IN1: f64 = 141.1;
+pub static IN2: f64 = 141.2;
+pub static SO0: f64 = 592.0;
+pub static SO1: f64 = 592.1;
+pub static SO2: f64 = 592.2;
+pub static WD: f64 = 931.0;
+pub static WA: f64 = 931.1;
+// the energy a massless component carries, MeV
+pub static EN: f64 = 367.0;
+// which of the species in decay.rs a particle is
+pub static KN: f64 = 577.0;
+// the flavors in the order q takes them, named as the species in decay.rs
+static FLAVOR: [&str; 6] = ["u", "d", "c", "s", "t", "b"];
+
+pub fn e(position: [f64; 3], inertia: [f64; 3], clock: bool) -> Anomaly {
     let sp = if clock { 0.5 } else { -0.5 };
     particle(
         position,
@@ -365,6 +584,18 @@ pub fn e(position: [f32; 3], inertia: [f64; 3], clock: bool) -> Anomaly {
                 name: SP,
                 value: sp,
             },
+            Property {
+                name: SO0,
+                value: 0.0,
+            },
+            Property {
+                name: SO1,
+                value: 0.0,
+            },
+            Property {
+                name: SO2,
+                value: 1.0,
+            },
             Property {
                 name: EC,
                 value: -1.0,
@@ -390,7 +621,7 @@ pub fn e(position: [f32; 3], inertia: [f64; 3], clock: bool) -> Anomaly {
 }
 
 pub fn q(
-    position: [f32; 3],
+    position: [f64; 3],
     inertia: [f64; 3],
     clock: bool,
     charge: bool,
@@ -398,7 +629,14 @@ pub fn q(
     flavor: u8,
 ) -> Anomaly {
     let sp = if clock { 0.5 } else { -0.5 };
-    let ch = if charge { 2.0 / 3.0 } else { -1.0 / 3.0 };
+    // colors 3 to 5 are anticolors, the antiquark carries the opposite charge
+    let (anti, sign) = if color % 6 >= 3 {
+        ("anti-", -1.0)
+    } else {
+        ("", 1.0)
+    };
+    let ch = sign * if charge { 2.0 / 3.0 } else { -1.0 / 3.0 };
+    let kind = species_named(&format!("{}{}", anti, FLAVOR[(flavor % 6) as usize])).unwrap();
 
     particle(
         position,
@@ -407,18 +645,34 @@ pub fn q(
                 name: SP,
                 value: sp,
             },
+            Property {
+                name: SO0,
+                value: 0.0,
+            },
+            Property {
+                name: SO1,
+                value: 0.0,
+            },
+            Property {
+                name: SO2,
+                value: 1.0,
+            },
             Property {
                 name: EC,
                 value: ch,
             },
             Property {
                 name: MS,
-                value: QMS[(flavor % 6) as usize],
+                value: SPECIES[kind].mass,
             },
             Property {
                 name: CR,
                 value: (color % 6) as f64,
             },
+            Property {
+                name: KN,
+                value: kind as f64,
+            },
             Property {
                 name: IN0,
                 value: inertia[0],
@@ -435,8 +689,33 @@ pub fn q(
     )
 }
 
-pub fn particular(coordinates: Vec<[f32; 3]>) -> Vec<[f32; 3]> {
-    return coordinates;
+// massless, so it keeps the speed of light along its inertia
+pub fn photon(position: [f64; 3], inertia: [f64; 3], energy: f64) -> Anomaly {
+    particle(
+        position,
+        vec![
+            Property {
+                name: EN,
+                value: energy,
+            },
+            Property {
+                name: MS,
+                value: 0.0,
+            },
+            Property {
+                name: IN0,
+                value: inertia[0],
+            },
+            Property {
+                name: IN1,
+                value: inertia[1],
+            },
+            Property {
+                name: IN2,
+                value: inertia[2],
+            },
+        ],
+    )
 }
 
 pub fn force_base() -> Force {
@@ -445,6 +724,7 @@ pub fn force_base() -> Force {
             Force {
                 force: vec![],
                 range: vec![1e-15],
+                law: vec![],
                 domain: vec![Component {
                     component: vec![],
                     composition: vec![],
@@ -452,11 +732,13 @@ pub fn force_base() -> Force {
                         name: CR,
                         value: 1.0,
                     }],
+                    id: Id::default(),
                 }],
             },
             Force {
                 force: vec![],
                 range: vec![f64::MAX],
+                law: vec![],
                 domain: vec![Component {
                     component: vec![],
                     composition: vec![],
@@ -464,6 +746,7 @@ pub fn force_base() -> Force {
                         name: EC,
                         value: 1.0 / 137.0,
                     }],
+                    id: Id::default(),
                 }],
             },
             Force {
@@ -471,6 +754,7 @@ pub fn force_base() -> Force {
                     Force {
                         force: vec![],
                         range: vec![1e-18],
+                        law: vec![],
                         domain: vec![Component {
                             component: vec![],
                             composition: vec![],
@@ -478,11 +762,13 @@ pub fn force_base() -> Force {
                                 name: MS,
                                 value: 1e-13,
                             }],
+                            id: Id::default(),
                         }],
                     },
                     Force {
                         force: vec![],
                         range: vec![1e-18],
+                        law: vec![],
                         domain: vec![Component {
                             component: vec![],
                             composition: vec![],
@@ -490,15 +776,18 @@ pub fn force_base() -> Force {
                                 name: SP,
                                 value: 1e-13,
                             }],
+                            id: Id::default(),
                         }],
                     },
                 ],
                 range: vec![],
+                law: vec![],
                 domain: vec![],
             },
             Force {
                 force: vec![],
                 range: vec![f64::MAX],
+                law: vec![],
                 domain: vec![Component {
                     component: vec![],
                     composition: vec![],
@@ -506,10 +795,68 @@ pub fn force_base() -> Force {
                         name: MS,
                         value: 1e-41,
                     }],
+                    id: Id::default(),
                 }],
             },
         ],
         range: vec![],
+        law: vec![],
         domain: vec![],
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::establish;
+
+    fn pair(root_force: bool) -> Anomaly {
+        let mut root = vacuum();
+        if root_force {
+            root.force = force_base().force;
+        }
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e([0.0, 0.0, 0.0], [0.0; 3], true));
+        add_particle_by(&mut root, top, e([1e3, 0.0, 0.0], [0.0; 3], true));
+        return root;
+    }
+
+    #[test]
+    fn far_particles_keep_their_shape_near_the_view_origin() {
+        let far = [1e15, -3e14, 7e13];
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e(far, [0.0; 3], true));
+
+        let x: Vec<f32> = view(&mut root, far)
+            .iter()
+            .flat_map(|s| s.positions.iter().map(|p| p.position[0]))
+            .collect();
+        let low = x.iter().fold(f32::MAX, |a, b| a.min(*b));
+        let high = x.iter().fold(f32::MIN, |a, b| a.max(*b));
+        assert!(high - low > 1.0 && high - low < 4.0);
+        assert!((high + low).abs() < 0.1);
+    }
+
+    #[test]
+    fn central_forces_are_applied_once_with_or_without_root_laws() {
+        for time in [TS_F64, 4.0 * TS_F64] {
+            let mut tree = pair(false);
+            let mut columns = pair(true);
+            progress(&mut tree, time);
+            progress(&mut columns, time);
+
+            let a = component_inertia(&tree.anomaly[0].component[0]);
+            let b = component_inertia(&columns.anomaly[0].component[0]);
+            assert!(a[0] < 0.0);
+            assert!(
+                (a[0] - b[0]).abs() <= 1e-9 * b[0].abs(),
+                "{} {}",
+                a[0],
+                b[0]
+            );
+        }
+    }
+}
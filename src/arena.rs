@@ -0,0 +1,379 @@
+use crate::anomaly::{Anomaly, Component};
+use crate::conservation::changed;
+use crate::query::{
+    anomaly_at, anomaly_at_mut, anomaly_path, component_at, component_at_mut, Path,
+};
+
+// generation 0 is never handed out, so a default id refers to nothing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id {
+    pub index: u32,
+    pub generation: u32,
+}
+
+struct Entry<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+pub struct Arena<T> {
+    entry: Vec<Entry<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        return Arena {
+            entry: vec![],
+            free: vec![],
+            len: 0,
+        };
+    }
+
+    pub fn insert(&mut self, value: T) -> Id {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let e = &mut self.entry[index as usize];
+            e.generation += 1;
+            e.value = Some(value);
+            return Id {
+                index,
+                generation: e.generation,
+            };
+        }
+
+        self.entry.push(Entry {
+            generation: 1,
+            value: Some(value),
+        });
+        return Id {
+            index: (self.entry.len() - 1) as u32,
+            generation: 1,
+        };
+    }
+
+    pub fn remove(&mut self, id: Id) -> Option<T> {
+        let e = self.entry.get_mut(id.index as usize)?;
+        if e.generation != id.generation {
+            return None;
+        }
+        let value = e.value.take()?;
+        self.free.push(id.index);
+        self.len -= 1;
+        return Some(value);
+    }
+
+    pub fn get(&self, id: Id) -> Option<&T> {
+        let e = self.entry.get(id.index as usize)?;
+        if e.generation != id.generation {
+            return None;
+        }
+        return e.value.as_ref();
+    }
+
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut T> {
+        let e = self.entry.get_mut(id.index as usize)?;
+        if e.generation != id.generation {
+            return None;
+        }
+        return e.value.as_mut();
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        return self.get(id).is_some();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Id, &T)> {
+        return self.entry.iter().enumerate().filter_map(|(k, e)| {
+            e.value.as_ref().map(|v| {
+                (
+                    Id {
+                        index: k as u32,
+                        generation: e.generation,
+                    },
+                    v,
+                )
+            })
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Anomaly,
+    Component,
+}
+
+pub struct Node {
+    pub kind: Kind,
+    pub parent: Option<Id>,
+    // where the node sits in the list of its parent
+    pub slot: usize,
+    pub child: Vec<Id>,
+}
+
+// the root anomaly keeps the ledger, the tree keeps the data and the ids. every
+// node knows its slot below its parent, so an id is found by following parent
+// links alone, however deep it sits and however many siblings it has
+pub struct Ledger {
+    pub node: Arena<Node>,
+}
+
+pub fn ledger_base() -> Ledger {
+    return Ledger { node: Arena::new() };
+}
+
+// give the root a ledger and an id to everything already below it
+pub fn establish(root: &mut Anomaly) {
+    let mut ledger = ledger_base();
+    register(&mut ledger, None, 0, root);
+    root.ledger = vec![ledger];
+}
+
+pub fn register(ledger: &mut Ledger, parent: Option<Id>, slot: usize, anom: &mut Anomaly) {
+    anom.id = ledger.node.insert(Node {
+        kind: Kind::Anomaly,
+        parent,
+        slot,
+        child: vec![],
+    });
+    link(ledger, parent, anom.id);
+
+    for (k, c) in anom.component.iter_mut().enumerate() {
+        register_component(ledger, anom.id, k, c);
+    }
+    for (k, a) in anom.anomaly.iter_mut().enumerate() {
+        register(ledger, Some(anom.id), k, a);
+    }
+}
+
+fn register_component(ledger: &mut Ledger, parent: Id, slot: usize, c: &mut Component) {
+    c.id = ledger.node.insert(Node {
+        kind: Kind::Component,
+        parent: Some(parent),
+        slot,
+        child: vec![],
+    });
+    link(ledger, Some(parent), c.id);
+
+    for (k, n) in c.component.iter_mut().enumerate() {
+        register_component(ledger, c.id, k, n);
+    }
+}
+
+fn link(ledger: &mut Ledger, parent: Option<Id>, id: Id) {
+    if let Some(p) = parent.and_then(|p| ledger.node.get_mut(p)) {
+        p.child.push(id);
+    }
+}
+
+pub fn unregister(ledger: &mut Ledger, id: Id) {
+    let node = match ledger.node.remove(id) {
+        Some(n) => n,
+        None => return,
+    };
+    if let Some(p) = node.parent.and_then(|p| ledger.node.get_mut(p)) {
+        p.child.retain(|c| *c != id);
+    }
+    for c in node.child {
+        unregister(ledger, c);
+    }
+}
+
+// moves a node under another parent, its id and everything below stay as they are
+fn reparent(ledger: &mut Ledger, id: Id, parent: Id, slot: usize) {
+    let old = match ledger.node.get_mut(id) {
+        Some(n) => {
+            n.slot = slot;
+            n.parent.replace(parent)
+        }
+        None => return,
+    };
+    if let Some(p) = old.and_then(|p| ledger.node.get_mut(p)) {
+        p.child.retain(|c| *c != id);
+    }
+    link(ledger, Some(parent), id);
+}
+
+// the siblings after a removed slot move up by one
+fn close_slot(ledger: &mut Ledger, sibling: &[Anomaly]) {
+    for a in sibling {
+        if let Some(n) = ledger.node.get_mut(a.id) {
+            n.slot -= 1;
+        }
+    }
+}
+
+pub fn parent_of(root: &Anomaly, id: Id) -> Option<Id> {
+    return root.ledger.first()?.node.get(id)?.parent;
+}
+
+pub fn children_of(root: &Anomaly, id: Id) -> Vec<Id> {
+    return match root.ledger.first().and_then(|l| l.node.get(id)) {
+        Some(n) => n.child.clone(),
+        None => vec![],
+    };
+}
+
+// where the id currently sits in the tree, the slots up the parent links
+pub fn path_of(root: &Anomaly, id: Id) -> Option<Path> {
+    let ledger = root.ledger.first()?;
+
+    let mut chain = vec![];
+    let mut at = id;
+    let mut node = ledger.node.get(id)?;
+    while let Some(p) = node.parent {
+        chain.push((node.kind, node.slot));
+        at = p;
+        node = ledger.node.get(p)?;
+    }
+    if at != root.id {
+        return None;
+    }
+
+    let mut path = Path::default();
+    for (kind, slot) in chain.iter().rev() {
+        match kind {
+            Kind::Anomaly => path.anomaly.push(*slot),
+            Kind::Component => path.component.push(*slot),
+        }
+    }
+
+    return Some(path);
+}
+
+pub fn anomaly_by_id(root: &Anomaly, id: Id) -> Option<&Anomaly> {
+    let path = path_of(root, id)?;
+    if path.is_component() {
+        return None;
+    }
+    return anomaly_at(root, &path).filter(|a| a.id == id);
+}
+
+pub fn anomaly_by_id_mut(root: &mut Anomaly, id: Id) -> Option<&mut Anomaly> {
+    let path = path_of(root, id)?;
+    if path.is_component() {
+        return None;
+    }
+    return anomaly_at_mut(root, &path).filter(|a| a.id == id);
+}
+
+pub fn component_by_id(root: &Anomaly, id: Id) -> Option<&Component> {
+    let path = path_of(root, id)?;
+    return component_at(root, &path).filter(|c| c.id == id);
+}
+
+pub fn component_by_id_mut(root: &mut Anomaly, id: Id) -> Option<&mut Component> {
+    let path = path_of(root, id)?;
+    return component_at_mut(root, &path).filter(|c| c.id == id);
+}
+
+// registers the anomaly and puts it below the parent, wherever that is
+pub fn insert_by_id(root: &mut Anomaly, parent: Id, mut anom: Anomaly) -> Option<Id> {
+    let slot = anomaly_by_id(root, parent)?.anomaly.len();
+    let mut ledger = root.ledger.pop()?;
+    register(&mut ledger, Some(parent), slot, &mut anom);
+    root.ledger.push(ledger);
+
+    let id = anom.id;
+    changed(root, &anom, 1.0, || {
+        format!("adding particle {} below {}", id.index, parent.index)
+    });
+    anomaly_by_id_mut(root, parent)?.anomaly.push(anom);
+    return Some(id);
+}
+
+// takes an anomaly out of the tree wherever it is, siblings keep their ids
+pub fn remove_by_id(root: &mut Anomaly, id: Id) -> Option<Anomaly> {
+    let removed = take_by_id(root, id)?;
+    if let Some(l) = root.ledger.first_mut() {
+        unregister(l, id);
+    }
+    changed(root, &removed, -1.0, || {
+        format!("removing particle {}", id.index)
+    });
+    return Some(removed);
+}
+
+// out of its parent's list, still in the ledger
+fn take_by_id(root: &mut Anomaly, id: Id) -> Option<Anomaly> {
+    let path = path_of(root, id)?;
+    if path.is_component() {
+        return None;
+    }
+    let (last, parent) = path.anomaly.split_last()?;
+    let mut ledger = root.ledger.pop()?;
+    let owner = anomaly_at_mut(root, &anomaly_path(parent.to_vec()));
+    let removed = owner.map(|o| {
+        let removed = o.anomaly.remove(*last);
+        close_slot(&mut ledger, &o.anomaly[*last..]);
+        removed
+    });
+    root.ledger.push(ledger);
+    return removed;
+}
+
+// moves an anomaly below another parent, it and everything below keep their ids
+pub fn move_by_id(root: &mut Anomaly, id: Id, parent: Id) -> Option<Id> {
+    let to = path_of(root, parent).filter(|p| !p.is_component())?;
+    if to.starts_with(&path_of(root, id)?) {
+        return None;
+    }
+    let anom = take_by_id(root, id)?;
+    let p = anomaly_by_id_mut(root, parent)?;
+    let slot = p.anomaly.len();
+    p.anomaly.push(anom);
+    if let Some(l) = root.ledger.first_mut() {
+        reparent(l, id, parent, slot);
+    }
+    return Some(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, e, vacuum};
+
+    #[test]
+    fn nested_particles_get_their_own_ids() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        let outer = add_particle_by(&mut root, top, vacuum()).unwrap();
+        let inner = add_particle_by(&mut root, outer, vacuum()).unwrap();
+        let a = add_particle_by(&mut root, inner, e([0.0; 3], [0.0; 3], true)).unwrap();
+        let b = add_particle_by(&mut root, inner, e([1.0; 3], [0.0; 3], true)).unwrap();
+
+        assert_ne!(a, Id::default());
+        assert_ne!(a, b);
+        assert_eq!(parent_of(&root, a), Some(inner));
+        assert_eq!(path_of(&root, b).unwrap().anomaly, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn siblings_are_found_after_removal_and_moves() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        let id: Vec<Id> = (0..5)
+            .map(|k| add_particle_by(&mut root, top, e([k as f64; 3], [0.0; 3], true)).unwrap())
+            .collect();
+        let group = add_particle_by(&mut root, top, vacuum()).unwrap();
+
+        assert!(remove_by_id(&mut root, id[1]).is_some());
+        assert!(anomaly_by_id(&root, id[1]).is_none());
+        assert_eq!(move_by_id(&mut root, id[3], group), Some(id[3]));
+        assert!(move_by_id(&mut root, group, group).is_none());
+
+        for k in [0, 2, 3, 4] {
+            assert_eq!(anomaly_by_id(&root, id[k]).unwrap().id, id[k]);
+        }
+        assert_eq!(parent_of(&root, id[3]), Some(group));
+        assert_eq!(children_of(&root, group), vec![id[3]]);
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Instant;
+
+// how many planck time steps the simulation owes, measured in wall time;
+// at speed 1 one step passes every sixtieth of a second whatever the frame rate
+
+pub static STEP_RATE: f64 = 60.0;
+// a slow frame catches up at most this many steps, the rest is dropped
+pub static STEP_LIMIT: u64 = 240;
+
+pub struct Clock {
+    pub paused: bool,
+    pub speed: f64,
+    pub steps: u64,
+    accumulator: f64,
+    pending: u64,
+    last: Instant,
+}
+
+pub fn clock_base() -> Clock {
+    return Clock {
+        paused: false,
+        speed: 1.0,
+        steps: 0,
+        accumulator: 0.0,
+        pending: 0,
+        last: Instant::now(),
+    };
+}
+
+// steps to run now, the fraction of a step left over waits for the next call
+pub fn steps_due(clock: &mut Clock) -> u64 {
+    let now = Instant::now();
+    let elapsed = now.duration_since(clock.last).as_secs_f64();
+    clock.last = now;
+
+    let mut due = clock.pending;
+    clock.pending = 0;
+    if !clock.paused {
+        clock.accumulator += elapsed * STEP_RATE * clock.speed;
+        let whole = clock.accumulator.floor();
+        clock.accumulator -= whole;
+        due += whole as u64;
+    }
+    if due > STEP_LIMIT {
+        due = STEP_LIMIT;
+    }
+
+    clock.steps += due;
+    return due;
+}
+
+pub fn toggle_pause(clock: &mut Clock) {
+    clock.paused = !clock.paused;
+    clock.accumulator = 0.0;
+}
+
+// one step while paused
+pub fn single_step(clock: &mut Clock) {
+    if clock.paused {
+        clock.pending += 1;
+    }
+}
+
+pub fn faster(clock: &mut Clock) {
+    clock.speed = (clock.speed * 2.0).min(64.0);
+}
+
+pub fn slower(clock: &mut Clock) {
+    clock.speed = (clock.speed / 2.0).max(1.0 / 64.0);
+}
@@ -0,0 +1,256 @@
+use crate::anomaly::{add_particle_by, vacuum, Anomaly, EP_F64, LS_F64};
+use crate::arena::{move_by_id, path_of, Id};
+use crate::columns::{central_laws, gather, separation, source_of, Central, Columns};
+use crate::f64_3::vector_length;
+use crate::query::Path;
+use crate::rigid::make_rigid;
+
+// groups of components closer to one another than a linking length, chained
+// through friends of friends; a group is bound when its kinetic energy around
+// its center of mass is smaller than the potential energy holding it together.
+// in a periodic box the distances are to the nearest image
+
+pub struct Cluster {
+    pub member: Vec<Id>,
+    pub path: Vec<Path>,
+    // MeV
+    pub kinetic: f64,
+    pub potential: f64,
+    pub bound: bool,
+}
+
+pub struct Membership {
+    pub clock: f64,
+    pub cluster: Vec<Cluster>,
+}
+
+// clusters every so many calls, keeping the memberships that changed
+pub struct Watch {
+    pub linking: f64,
+    pub every: u64,
+    pub regroup: bool,
+    // regrouped composites move as rigid bodies
+    pub rigid: bool,
+    pub log: Vec<Membership>,
+    calls: u64,
+}
+
+pub fn watch_base(linking: f64, every: u64, regroup: bool, rigid: bool) -> Watch {
+    return Watch {
+        linking,
+        every: every.max(1),
+        regroup: regroup || rigid,
+        rigid,
+        log: vec![],
+        calls: 0,
+    };
+}
+
+pub fn find(parent: &mut Vec<usize>, k: usize) -> usize {
+    let mut k = k;
+    while parent[k] != k {
+        parent[k] = parent[parent[k]];
+        k = parent[k];
+    }
+    return k;
+}
+
+// column indices of every group with more than one member, linking length in planck lengths
+pub fn friends_of_friends(columns: &Columns, linking: f64) -> Vec<Vec<usize>> {
+    let n = columns.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    for i in 0..n {
+        for j in i + 1..n {
+            if vector_length(separation(columns, i, j)) <= linking {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut group: Vec<Vec<usize>> = vec![vec![]; n];
+    for k in 0..n {
+        let r = find(&mut parent, k);
+        group[r].push(k);
+    }
+
+    return group.into_iter().filter(|g| g.len() > 1).collect();
+}
+
+// kinetic energy around the center of mass and the potential of the central laws, MeV
+pub fn energies(columns: &Columns, laws: &Vec<Central>, group: &Vec<usize>) -> (f64, f64) {
+    let mut total = 0.0;
+    let mut momentum = [0.0, 0.0, 0.0];
+    for k in group {
+        let v = columns.inertia(*k);
+        total += columns.mass[*k];
+        for i in 0..3 {
+            momentum[i] += columns.mass[*k] * v[i];
+        }
+    }
+    if total <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let mut kinetic = 0.0;
+    for k in group {
+        let v = columns.inertia(*k);
+        let mut u2 = 0.0;
+        for i in 0..3 {
+            u2 += (v[i] - momentum[i] / total).powi(2);
+        }
+        kinetic += 0.5 * columns.mass[*k] * u2 / (LS_F64 * LS_F64);
+    }
+
+    let mut potential = 0.0;
+    for law in laws {
+        let s = source_of(columns, law.source);
+        for (x, i) in group.iter().enumerate() {
+            for j in &group[x + 1..] {
+                let d = vector_length(separation(columns, *i, *j));
+                if d == 0.0 || d > law.reach {
+                    continue;
+                }
+                potential += law.sign * law.coupling * EP_F64 * s[*i] * s[*j] / d;
+            }
+        }
+    }
+
+    return (kinetic, potential);
+}
+
+pub fn clusters(anom: &Anomaly, linking: f64) -> Vec<Cluster> {
+    let columns = gather(anom);
+    let laws = central_laws(&anom.force);
+
+    return friends_of_friends(&columns, linking)
+        .into_iter()
+        .map(|g| {
+            let (kinetic, potential) = energies(&columns, &laws, &g);
+            Cluster {
+                member: g.iter().map(|k| columns.id[*k]).collect(),
+                path: g.iter().map(|k| columns.path[*k].clone()).collect(),
+                kinetic,
+                potential,
+                bound: kinetic + potential < 0.0,
+            }
+        })
+        .collect();
+}
+
+// moves the particles owning the members under one new composite anomaly;
+// only particles sitting directly below the root are taken, ids stay the same
+pub fn regroup(root: &mut Anomaly, cluster: &Cluster) -> Option<Id> {
+    let mut owner: Vec<Id> = cluster
+        .member
+        .iter()
+        .filter_map(|m| path_of(root, *m))
+        .filter(|p| p.anomaly.len() == 1)
+        .map(|p| root.anomaly[p.anomaly[0]].id)
+        .collect();
+    owner.sort();
+    owner.dedup();
+    if owner.len() < 2 {
+        return None;
+    }
+
+    let composite = Anomaly {
+        yukawa: root.yukawa.clone(),
+        ..vacuum()
+    };
+    let parent = root.id;
+    let id = add_particle_by(root, parent, composite)?;
+    for o in &owner {
+        move_by_id(root, *o, id);
+    }
+
+    return Some(id);
+}
+
+pub fn watch_step(w: &mut Watch, anom: &mut Anomaly) {
+    w.calls += 1;
+    if w.calls % w.every != 0 {
+        return;
+    }
+
+    let cluster = clusters(anom, w.linking);
+    let changed = match w.log.last() {
+        Some(m) => !same_membership(&m.cluster, &cluster),
+        None => !cluster.is_empty(),
+    };
+
+    if w.regroup {
+        let size = anom.periodic.first().map(|p| p.size);
+        for c in cluster.iter().filter(|c| c.bound) {
+            let id = regroup(anom, c);
+            if !w.rigid {
+                continue;
+            }
+            if let Some(composite) = anom.anomaly.iter_mut().find(|a| Some(a.id) == id) {
+                make_rigid(composite, size);
+            }
+        }
+    }
+    if changed {
+        anom.log.push(membership_line(anom.clock, &cluster));
+        w.log.push(Membership {
+            clock: anom.clock,
+            cluster,
+        });
+    }
+}
+
+fn same_membership(a: &Vec<Cluster>, b: &Vec<Cluster>) -> bool {
+    let key = |c: &Vec<Cluster>| {
+        let mut k: Vec<(Vec<Id>, bool)> = c
+            .iter()
+            .map(|x| {
+                let mut m = x.member.clone();
+                m.sort();
+                (m, x.bound)
+            })
+            .collect();
+        k.sort();
+        k
+    };
+    return key(a) == key(b);
+}
+
+// one line per change: the clock, then every cluster as its member indices, bound ones starred
+pub fn membership_line(clock: f64, cluster: &Vec<Cluster>) -> String {
+    let mut s = format!("clusters at {:e} s:", clock);
+    for c in cluster {
+        let member: Vec<String> = c.member.iter().map(|i| i.index.to_string()).collect();
+        s = format!(
+            "{} [{}]{}",
+            s,
+            member.join(" "),
+            if c.bound { "*" } else { "" }
+        );
+    }
+    return s;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::anomaly::e;
+    use crate::arena::establish;
+    use crate::ewald::{ewald_base, periodic_base};
+
+    #[test]
+    fn friends_link_across_the_periodic_box() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e([-48.0, 0.0, 0.0], [0.0; 3], true));
+        add_particle_by(&mut root, top, e([48.0, 0.0, 0.0], [0.0; 3], true));
+        assert!(friends_of_friends(&gather(&root), 5.0).is_empty());
+
+        let size = [100.0, 100.0, 100.0];
+        root.periodic = vec![Arc::new(ewald_base(&periodic_base(size, 0.0)))];
+        assert_eq!(friends_of_friends(&gather(&root), 5.0), vec![vec![0, 1]]);
+    }
+}
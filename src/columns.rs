@@ -0,0 +1,343 @@
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+
+use crate::anomaly::{
+    component_inertia, component_position, component_property, has_component_property, set_inertia,
+    Anomaly, Force, EC, EP_F64, IN0, LS_F64, ML_F64, MS, TS_F64,
+};
+use crate::arena::Id;
+use crate::ewald::{ewald_forces, nearest_image, Ewald};
+use crate::force::{central_sign, is_central};
+use crate::query::{component_at_mut, components_depth_first, Path};
+use crate::wavepacket::{smeared, wavepacket_width};
+
+// particle state packed column by column, so the per step loops run over
+// contiguous slices; gathered from the tree before stepping and scattered back
+// after, the tree stays what rendering and editing look at
+
+pub struct Columns {
+    pub id: Vec<Id>,
+    pub path: Vec<Path>,
+    pub px: Vec<f64>,
+    pub py: Vec<f64>,
+    pub pz: Vec<f64>,
+    pub ix: Vec<f64>,
+    pub iy: Vec<f64>,
+    pub iz: Vec<f64>,
+    pub mass: Vec<f64>,
+    pub charge: Vec<f64>,
+    // wavepacket widths, 0 for points
+    pub width: Vec<f64>,
+    // the box of the anomaly gathered, when it is periodic
+    pub periodic: Vec<Arc<Ewald>>,
+    origin: Vec<[f64; 3]>,
+}
+
+impl Columns {
+    pub fn len(&self) -> usize {
+        return self.px.len();
+    }
+
+    pub fn position(&self, k: usize) -> [f64; 3] {
+        return [self.px[k], self.py[k], self.pz[k]];
+    }
+
+    pub fn inertia(&self, k: usize) -> [f64; 3] {
+        return [self.ix[k], self.iy[k], self.iz[k]];
+    }
+}
+
+// every component that moves, nested ones included
+pub fn gather(anom: &Anomaly) -> Columns {
+    let mut columns = Columns {
+        id: vec![],
+        path: vec![],
+        px: vec![],
+        py: vec![],
+        pz: vec![],
+        ix: vec![],
+        iy: vec![],
+        iz: vec![],
+        mass: vec![],
+        charge: vec![],
+        width: vec![],
+        periodic: anom.periodic.clone(),
+        origin: vec![],
+    };
+
+    for (path, c) in components_depth_first(anom, &Path::default()) {
+        if !has_component_property(c, IN0) || !has_component_property(c, MS) {
+            continue;
+        }
+        let p = component_position(c);
+        let i = component_inertia(c);
+
+        columns.id.push(c.id);
+        columns.path.push(path);
+        columns.px.push(p[0]);
+        columns.py.push(p[1]);
+        columns.pz.push(p[2]);
+        columns.ix.push(i[0]);
+        columns.iy.push(i[1]);
+        columns.iz.push(i[2]);
+        columns.mass.push(component_property(c, MS));
+        columns.charge.push(if has_component_property(c, EC) {
+            component_property(c, EC)
+        } else {
+            0.0
+        });
+        columns.width.push(wavepacket_width(c).unwrap_or(0.0));
+        columns.origin.push(p);
+    }
+
+    return columns;
+}
+
+// writes inertia back and moves every point of a component by how far its center went
+pub fn scatter(columns: &Columns, anom: &mut Anomaly) {
+    for k in 0..columns.len() {
+        let c = match component_at_mut(anom, &columns.path[k]) {
+            Some(c) => c,
+            None => continue,
+        };
+        set_inertia(columns.inertia(k), c);
+
+        let o = columns.origin[k];
+        let shift = [
+            columns.px[k] - o[0],
+            columns.py[k] - o[1],
+            columns.pz[k] - o[2],
+        ];
+        for s in c.composition.iter_mut() {
+            for v in s.space.iter_mut() {
+                v[0] += shift[0];
+                v[1] += shift[1];
+                v[2] += shift[2];
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    Charge,
+    Mass,
+}
+
+// an inverse square law between two columns of the same source,
+// like sources repel with a positive sign
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Central {
+    pub source: Source,
+    pub coupling: f64,
+    pub sign: f64,
+    pub reach: f64,
+}
+
+// the top level inverse square laws of an anomaly, nested forces stay with force_apply
+pub fn central_laws(forces: &Vec<Force>) -> Vec<Central> {
+    return forces.iter().filter_map(central_law).collect();
+}
+
+pub fn central_law(f: &Force) -> Option<Central> {
+    if !is_central(f) {
+        return None;
+    }
+    let reach = match f.range.first() {
+        Some(r) if *r < f64::MAX / ML_F64 => r * ML_F64,
+        _ => f64::MAX,
+    };
+    let p = &f.domain[0].property[0];
+    return Some(Central {
+        source: if p.name == EC {
+            Source::Charge
+        } else {
+            Source::Mass
+        },
+        coupling: p.value,
+        sign: central_sign(p.name),
+        reach,
+    });
+}
+
+// a separation between columns, to the nearest image in a periodic box
+pub fn shortest(columns: &Columns, d: [f64; 3]) -> [f64; 3] {
+    return match columns.periodic.first() {
+        Some(e) => nearest_image(d, e.size),
+        None => d,
+    };
+}
+
+// from column j to column i
+pub fn separation(columns: &Columns, i: usize, j: usize) -> [f64; 3] {
+    let d = [
+        columns.px[i] - columns.px[j],
+        columns.py[i] - columns.py[j],
+        columns.pz[i] - columns.pz[j],
+    ];
+    return shortest(columns, d);
+}
+
+pub fn source_of<'a>(columns: &'a Columns, source: Source) -> &'a Vec<f64> {
+    return match source {
+        Source::Charge => &columns.charge,
+        Source::Mass => &columns.mass,
+    };
+}
+
+// force on k from every other column, MeV per planck length; in a periodic box
+// from the nearest image, the electric laws being left to the ewald sums
+fn central_force(columns: &Columns, laws: &Vec<Central>, k: usize) -> [f64; 3] {
+    let mut f = [0.0, 0.0, 0.0];
+    let (px, py, pz) = (&columns.px, &columns.py, &columns.pz);
+    let periodic = columns.periodic.first();
+
+    for law in laws {
+        let s = source_of(columns, law.source);
+        if s[k] == 0.0 || (periodic.is_some() && law.source == Source::Charge) {
+            continue;
+        }
+        let strength = law.sign * law.coupling * EP_F64 * s[k];
+        for j in 0..columns.len() {
+            let mut d = [px[k] - px[j], py[k] - py[j], pz[k] - pz[j]];
+            if let Some(e) = periodic {
+                d = nearest_image(d, e.size);
+            }
+            let [dx, dy, dz] = d;
+            let d2 = dx * dx + dy * dy + dz * dz;
+            if d2 == 0.0 || d2 > law.reach * law.reach {
+                continue;
+            }
+            let mut w = strength * s[j] / (d2 * d2.sqrt());
+            let spread = columns.width[k].powi(2) + columns.width[j].powi(2);
+            if spread > 0.0 {
+                w *= smeared(d2.sqrt(), spread.sqrt());
+            }
+            f[0] += w * dx;
+            f[1] += w * dy;
+            f[2] += w * dz;
+        }
+    }
+
+    return f;
+}
+
+// evaluates every pair and turns the forces into inertia over time
+pub fn central_kernel(columns: &mut Columns, laws: &Vec<Central>, time: f64) {
+    let n = columns.len();
+    if n < 2 || laws.is_empty() {
+        return;
+    }
+    let force = (0..n).map(|k| central_force(columns, laws, k)).collect();
+    apply_central(columns, laws, force, time);
+}
+
+// the ewald sums on top of the pairs, then the forces into inertia
+fn apply_central(columns: &mut Columns, laws: &Vec<Central>, mut force: Vec<[f64; 3]>, time: f64) {
+    if let Some(e) = columns.periodic.first() {
+        for law in laws.iter().filter(|l| l.source == Source::Charge) {
+            let strength = law.sign * law.coupling * EP_F64;
+            for (k, f) in ewald_forces(columns, e, strength).iter().enumerate() {
+                for x in 0..3 {
+                    force[k][x] += f[x];
+                }
+            }
+        }
+    }
+
+    for k in 0..force.len() {
+        accelerate_column(columns, k, force[k], time);
+    }
+}
+
+// the column counterpart of accelerate
+pub fn accelerate_column(columns: &mut Columns, k: usize, force: [f64; 3], time: f64) {
+    let mass = columns.mass[k];
+    if mass <= 0.0 {
+        return;
+    }
+    let scale = LS_F64 * LS_F64 * time / mass;
+    columns.ix[k] += force[0] * scale;
+    columns.iy[k] += force[1] * scale;
+    columns.iz[k] += force[2] * scale;
+    limit_column(columns, k);
+}
+
+fn limit_column(columns: &mut Columns, k: usize) {
+    let v2 = columns.ix[k].powi(2) + columns.iy[k].powi(2) + columns.iz[k].powi(2);
+    if v2 > LS_F64 * LS_F64 {
+        let scale = LS_F64 / v2.sqrt();
+        columns.ix[k] *= scale;
+        columns.iy[k] *= scale;
+        columns.iz[k] *= scale;
+    }
+}
+
+pub fn integrate(columns: &mut Columns, time: f64) {
+    for (p, i) in columns.px.iter_mut().zip(&columns.ix) {
+        *p += i * time;
+    }
+    for (p, i) in columns.py.iter_mut().zip(&columns.iy) {
+        *p += i * time;
+    }
+    for (p, i) in columns.pz.iter_mut().zip(&columns.iz) {
+        *p += i * time;
+    }
+}
+
+// planck time steps until the time is used up; the pairs are split between
+// workers started once for all the steps, each reading the columns for its
+// share while the stepping waits, the stepping writing them while they wait
+pub fn step_columns(columns: &mut Columns, laws: &Vec<Central>, time: f64) {
+    let steps = (time / TS_F64) as u64;
+    let n = columns.len();
+    let workers = thread::available_parallelism()
+        .map_or(1, |w| w.get())
+        .min(n);
+    if workers < 2 || laws.is_empty() {
+        for _ in 0..steps {
+            central_kernel(columns, laws, TS_F64);
+            integrate(columns, TS_F64);
+        }
+        return;
+    }
+
+    let chunk = n.div_ceil(workers);
+    let shared = RwLock::new(columns);
+    thread::scope(|s| {
+        let (done, finished) = mpsc::channel();
+        let mut start = vec![];
+        for w in 0..n.div_ceil(chunk) {
+            let (tx, rx) = mpsc::channel::<()>();
+            start.push(tx);
+            let done = done.clone();
+            let shared = &shared;
+            s.spawn(move || {
+                for _ in rx {
+                    let columns = shared.read().unwrap();
+                    let part: Vec<[f64; 3]> = (w * chunk..n.min((w + 1) * chunk))
+                        .map(|k| central_force(&columns, laws, k))
+                        .collect();
+                    drop(columns);
+                    done.send((w, part)).unwrap();
+                }
+            });
+        }
+
+        for _ in 0..steps {
+            for tx in &start {
+                tx.send(()).unwrap();
+            }
+            let mut force = vec![[0.0, 0.0, 0.0]; n];
+            for _ in 0..start.len() {
+                let (w, part) = finished.recv().unwrap();
+                force[w * chunk..w * chunk + part.len()].copy_from_slice(&part);
+            }
+            let mut columns = shared.write().unwrap();
+            apply_central(&mut columns, laws, force, TS_F64);
+            integrate(&mut columns, TS_F64);
+        }
+        // the workers end with their channels
+        drop(start);
+    });
+}
@@ -0,0 +1,337 @@
+use std::sync::Arc;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet,
+    },
+    device::{
+        physical::PhysicalDeviceType, Device, DeviceCreateInfo, DeviceOwned, Queue,
+        QueueCreateInfo, QueueFlags,
+    },
+    instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, ComputePipeline, Pipeline, PipelineBindPoint,
+        PipelineLayout, PipelineShaderStageCreateInfo,
+    },
+    sync::{self, GpuFuture},
+    VulkanLibrary,
+};
+
+use crate::anomaly::{Anomaly, EP_F64, LS_F64, TS_F64};
+use crate::columns::{central_laws, gather, source_of, step_columns, Central, Columns};
+
+// the column stepping of progress on a vulkan device; the shader works in
+// f32 step units (see pairwise.glsl), positions relative to the first column
+
+pub static COMPUTE_TOLERANCE: f64 = 1e-3;
+
+pub struct Compute {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    pipeline: Arc<ComputePipeline>,
+}
+
+// None when the queue can not run compute work
+pub fn compute_base(
+    queue: &Arc<Queue>,
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: &Arc<StandardDescriptorSetAllocator>,
+    command_buffer_allocator: &Arc<StandardCommandBufferAllocator>,
+) -> Option<Compute> {
+    let device = queue.device().clone();
+    let family =
+        &device.physical_device().queue_family_properties()[queue.queue_family_index() as usize];
+    if !family.queue_flags.intersects(QueueFlags::COMPUTE) {
+        return None;
+    }
+
+    let cs = cs::load(&device).unwrap().entry_point("main").unwrap();
+    let stage = PipelineShaderStageCreateInfo::new(&cs);
+    let layout = PipelineLayout::from_stages(&device, &[stage.clone()]).unwrap();
+    let pipeline = ComputePipeline::new(
+        &device,
+        None,
+        &ComputePipelineCreateInfo::new(stage, &layout),
+    )
+    .unwrap();
+
+    return Some(Compute {
+        device,
+        queue: queue.clone(),
+        memory_allocator: memory_allocator.clone(),
+        descriptor_set_allocator: descriptor_set_allocator.clone(),
+        command_buffer_allocator: command_buffer_allocator.clone(),
+        pipeline,
+    });
+}
+
+// a device of its own without any window, a cpu implementation like lavapipe will do
+pub fn compute_headless() -> Option<Compute> {
+    let library = VulkanLibrary::new().ok()?;
+    let instance = Instance::new(
+        &library,
+        &InstanceCreateInfo {
+            flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+            ..Default::default()
+        },
+    )
+    .ok()?;
+
+    let (physical_device, queue_family_index) = instance
+        .enumerate_physical_devices()
+        .ok()?
+        .filter_map(|p| {
+            p.queue_family_properties()
+                .iter()
+                .position(|q| q.queue_flags.intersects(QueueFlags::COMPUTE))
+                .map(|i| (p, i as u32))
+        })
+        .min_by_key(|(p, _)| match p.properties().device_type {
+            PhysicalDeviceType::DiscreteGpu => 0,
+            PhysicalDeviceType::IntegratedGpu => 1,
+            PhysicalDeviceType::VirtualGpu => 2,
+            PhysicalDeviceType::Cpu => 3,
+            PhysicalDeviceType::Other => 4,
+            _ => 5,
+        })?;
+
+    println!(
+        "Computing on: {} (type: {:?})",
+        physical_device.properties().device_name,
+        physical_device.properties().device_type,
+    );
+
+    let (device, mut queues) = Device::new(
+        &physical_device,
+        &DeviceCreateInfo {
+            queue_create_infos: &[QueueCreateInfo {
+                queue_family_index,
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    )
+    .ok()?;
+    let queue = queues.next()?;
+
+    let memory_allocator = Arc::new(StandardMemoryAllocator::new(&device, &Default::default()));
+    let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
+        &device,
+        &Default::default(),
+    ));
+    let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+        &device,
+        &Default::default(),
+    ));
+
+    return compute_base(
+        &queue,
+        &memory_allocator,
+        &descriptor_set_allocator,
+        &command_buffer_allocator,
+    );
+}
+
+fn storage<T: BufferContents>(compute: &Compute, data: Vec<T>) -> Subbuffer<[T]> {
+    return Buffer::from_iter(
+        &compute.memory_allocator,
+        &BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        &AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data,
+    )
+    .unwrap();
+}
+
+// the same steps as step_columns, uploaded once and read back once
+pub fn compute_step(compute: &Compute, columns: &mut Columns, laws: &Vec<Central>, time: f64) {
+    // the ewald sums of a periodic box are only stepped on the cpu
+    if !columns.periodic.is_empty() {
+        return step_columns(columns, laws, time);
+    }
+    let n = columns.len();
+    let steps = (time / TS_F64) as u64;
+    if n == 0 || steps == 0 {
+        return;
+    }
+
+    let center = columns.position(0);
+    let position = storage(
+        compute,
+        (0..n)
+            .map(|k| {
+                [
+                    (columns.px[k] - center[0]) as f32,
+                    (columns.py[k] - center[1]) as f32,
+                    (columns.pz[k] - center[2]) as f32,
+                    0.0,
+                ]
+            })
+            .collect(),
+    );
+    let velocity = storage(
+        compute,
+        (0..n)
+            .map(|k| {
+                [
+                    (columns.ix[k] * TS_F64) as f32,
+                    (columns.iy[k] * TS_F64) as f32,
+                    (columns.iz[k] * TS_F64) as f32,
+                    0.0,
+                ]
+            })
+            .collect(),
+    );
+
+    // what accelerate_column does with a force, folded into one factor per column
+    let unit = (LS_F64 * TS_F64).powi(2);
+    let mut strength = vec![];
+    let mut source = vec![];
+    let mut reach = vec![];
+    for law in laws {
+        let s = source_of(columns, law.source);
+        for k in 0..n {
+            strength.push(if columns.mass[k] > 0.0 {
+                (law.sign * law.coupling * EP_F64 * s[k] * unit / columns.mass[k]) as f32
+            } else {
+                0.0
+            });
+            source.push(s[k] as f32);
+        }
+        reach.push(law.reach as f32);
+    }
+    // empty storage buffers can not be bound
+    strength.push(0.0);
+    source.push(0.0);
+    reach.push(0.0);
+    let width = storage(
+        compute,
+        columns
+            .width
+            .iter()
+            .map(|w| *w as f32)
+            .collect::<Vec<f32>>(),
+    );
+    let strength = storage(compute, strength);
+    let source = storage(compute, source);
+    let reach = storage(compute, reach);
+
+    let layout = &compute.pipeline.layout().set_layouts()[0];
+    let descriptor_set = DescriptorSet::new(
+        compute.descriptor_set_allocator.clone(),
+        layout.clone(),
+        [
+            WriteDescriptorSet::buffer(0, position.clone()),
+            WriteDescriptorSet::buffer(1, velocity.clone()),
+            WriteDescriptorSet::buffer(2, strength),
+            WriteDescriptorSet::buffer(3, source),
+            WriteDescriptorSet::buffer(4, reach),
+            WriteDescriptorSet::buffer(5, width),
+        ],
+        [],
+    )
+    .unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        compute.command_buffer_allocator.clone(),
+        compute.queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+    builder
+        .bind_pipeline_compute(compute.pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            compute.pipeline.layout().clone(),
+            0,
+            descriptor_set,
+        )
+        .unwrap();
+
+    let groups = (n as u32).div_ceil(64);
+    for _ in 0..steps {
+        for stage in 0..2 {
+            let push = cs::Step {
+                count: n as u32,
+                laws: laws.len() as u32,
+                stage,
+                limit: (LS_F64 * TS_F64) as f32,
+            };
+            builder
+                .push_constants(compute.pipeline.layout().clone(), 0, push)
+                .unwrap();
+            unsafe {
+                builder.dispatch([groups, 1, 1]).unwrap();
+            }
+        }
+    }
+
+    let command_buffer = builder.build().unwrap();
+    sync::now(compute.device.clone())
+        .then_execute(compute.queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let position = position.read().unwrap();
+    let velocity = velocity.read().unwrap();
+    for k in 0..n {
+        columns.px[k] = center[0] + position[k][0] as f64;
+        columns.py[k] = center[1] + position[k][1] as f64;
+        columns.pz[k] = center[2] + position[k][2] as f64;
+        columns.ix[k] = velocity[k][0] as f64 / TS_F64;
+        columns.iy[k] = velocity[k][1] as f64 / TS_F64;
+        columns.iz[k] = velocity[k][2] as f64 / TS_F64;
+    }
+}
+
+// steps the same columns on both paths, the largest difference in position
+// (against how far the column went, at least a planck length) or in
+// velocity (against light speed)
+pub fn compute_check(compute: &Compute, anom: &Anomaly, time: f64) -> f64 {
+    let laws = central_laws(&anom.force);
+    let mut cpu = gather(anom);
+    let mut gpu = gather(anom);
+    step_columns(&mut cpu, &laws, time);
+    compute_step(compute, &mut gpu, &laws, time);
+
+    let start = gather(anom);
+    let mut deviation: f64 = 0.0;
+    for k in 0..cpu.len() {
+        let (c, g, s) = (cpu.position(k), gpu.position(k), start.position(k));
+        let went = ((c[0] - s[0]).powi(2) + (c[1] - s[1]).powi(2) + (c[2] - s[2]).powi(2))
+            .sqrt()
+            .max(1.0);
+        let off = ((c[0] - g[0]).powi(2) + (c[1] - g[1]).powi(2) + (c[2] - g[2]).powi(2)).sqrt();
+        deviation = deviation.max(off / went);
+
+        let (c, g) = (cpu.inertia(k), gpu.inertia(k));
+        let off = ((c[0] - g[0]).powi(2) + (c[1] - g[1]).powi(2) + (c[2] - g[2]).powi(2)).sqrt();
+        deviation = deviation.max(off / LS_F64);
+    }
+
+    return deviation;
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "./src/pairwise.glsl",
+    }
+}
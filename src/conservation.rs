@@ -0,0 +1,295 @@
+use crate::anomaly::{component_property, has_component_property, Anomaly, CR, EC};
+use crate::arena::Id;
+use crate::decay::{species_of, SPECIES};
+use crate::query::{anomalies_depth_first, Path};
+
+// sums what every change of the tree has to keep and compares the sums from
+// before and after each change that makes or takes particles. insert_by_id and
+// remove_by_id hand every particle they add or take to the validator of the
+// root, which keeps the sums of the whole tree up to date; a change is an
+// event of its own unless it falls between begin and end, so the photons of
+// radiation, every decay and every reaction are checked as a whole. moving a
+// particle keeps every sum. a change that breaks a law is reported with the
+// laws it broke and the event that did it
+//
+// charge and color come from the EC and CR of every component; baryon and
+// lepton numbers from the species of every particle, components of no species
+// counting a third of a baryon per quark. color is counted up to a colorless
+// whole: red less antired against blue less antiblue, green likewise
+
+pub static QUANTUM_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quanta {
+    pub charge: f64,
+    pub baryon: f64,
+    // electron, muon and tau numbers
+    pub lepton: [f64; 3],
+    pub color: [f64; 2],
+}
+
+pub struct Violation {
+    pub law: &'static str,
+    pub before: f64,
+    pub after: f64,
+}
+
+pub struct Report {
+    // s, the clock of the root
+    pub clock: f64,
+    pub event: String,
+    pub violation: Vec<Violation>,
+}
+
+pub struct Validator {
+    pub checked: u64,
+    pub report: Vec<Report>,
+    // lines not yet taken into a snapshot
+    pub log: Vec<String>,
+    // the sums of the whole tree, kept up with every change
+    pub total: Quanta,
+    // events begun and not ended, and the sums from before the outermost
+    open: u32,
+    start: Quanta,
+}
+
+pub fn validator_base() -> Validator {
+    return Validator {
+        checked: 0,
+        report: vec![],
+        log: vec![],
+        total: Quanta::default(),
+        open: 0,
+        start: Quanta::default(),
+    };
+}
+
+// a validator held to the sums of the tree as it is, noting the particles it
+// cannot place
+pub fn validator_of(root: &Anomaly) -> Validator {
+    let total = quanta(root);
+    let mut log = vec![format!("validate: {}", quanta_line(&total))];
+    for id in unknown_particles(root) {
+        log.push(format!("validate: particle {} is of no species", id.index));
+    }
+    return Validator {
+        checked: 0,
+        report: vec![],
+        log,
+        total,
+        open: 0,
+        start: total,
+    };
+}
+
+pub fn quanta(root: &Anomaly) -> Quanta {
+    let mut q = Quanta::default();
+    let mut count = [0.0; 6];
+
+    for (_, a) in anomalies_depth_first(root, &Path::default()) {
+        let species = species_of(a);
+        if let Some(k) = species {
+            q.baryon += SPECIES[k].baryon;
+            for x in 0..3 {
+                q.lepton[x] += SPECIES[k].lepton[x] as f64;
+            }
+        }
+        let mut stack: Vec<_> = a.component.iter().collect();
+        while let Some(c) = stack.pop() {
+            if has_component_property(c, EC) {
+                q.charge += component_property(c, EC);
+            }
+            if has_component_property(c, CR) {
+                let color = (component_property(c, CR) as usize) % 6;
+                count[color] += 1.0;
+                if species.is_none() {
+                    q.baryon += if color < 3 { 1.0 / 3.0 } else { -1.0 / 3.0 };
+                }
+            }
+            stack.extend(c.component.iter());
+        }
+    }
+
+    let blue = count[2] - count[5];
+    q.color = [count[0] - count[3] - blue, count[1] - count[4] - blue];
+    return q;
+}
+
+pub fn violations(before: &Quanta, after: &Quanta) -> Vec<Violation> {
+    let law: [(&'static str, f64, f64); 7] = [
+        ("charge", before.charge, after.charge),
+        ("baryon number", before.baryon, after.baryon),
+        ("electron number", before.lepton[0], after.lepton[0]),
+        ("muon number", before.lepton[1], after.lepton[1]),
+        ("tau number", before.lepton[2], after.lepton[2]),
+        ("red", before.color[0], after.color[0]),
+        ("green", before.color[1], after.color[1]),
+    ];
+    return law
+        .iter()
+        .filter(|(_, b, a)| (a - b).abs() > QUANTUM_TOLERANCE)
+        .map(|(law, b, a)| Violation {
+            law,
+            before: *b,
+            after: *a,
+        })
+        .collect();
+}
+
+fn shifted(q: &Quanta, by: &Quanta, sign: f64) -> Quanta {
+    return Quanta {
+        charge: q.charge + sign * by.charge,
+        baryon: q.baryon + sign * by.baryon,
+        lepton: [0, 1, 2].map(|x| q.lepton[x] + sign * by.lepton[x]),
+        color: [0, 1].map(|x| q.color[x] + sign * by.color[x]),
+    };
+}
+
+// the changes until the matching end are one event
+pub fn begin(root: &mut Anomaly) {
+    if let Some(v) = root.validator.first_mut() {
+        if v.open == 0 {
+            v.start = v.total;
+        }
+        v.open += 1;
+    }
+}
+
+// compares with the sums from before the event and reports what it broke
+pub fn end(root: &mut Anomaly, event: &str) {
+    let clock = root.clock;
+    let v = match root.validator.first_mut() {
+        Some(v) if v.open > 0 => v,
+        _ => return,
+    };
+    v.open -= 1;
+    if v.open > 0 {
+        return;
+    }
+    v.checked += 1;
+    let violation = violations(&v.start, &v.total);
+    if violation.is_empty() {
+        return;
+    }
+
+    let report = Report {
+        clock,
+        event: event.to_string(),
+        violation,
+    };
+    v.log.push(report_line(&report));
+    v.report.push(report);
+}
+
+// a particle added (sign 1) or taken (sign -1) somewhere below the root
+pub fn changed(root: &mut Anomaly, part: &Anomaly, sign: f64, event: impl Fn() -> String) {
+    if root.validator.is_empty() {
+        return;
+    }
+    let q = quanta(part);
+    begin(root);
+    if let Some(v) = root.validator.first_mut() {
+        v.total = shifted(&v.total, &q, sign);
+    }
+    end(root, &event());
+}
+
+// particles no species has, whose baryon and lepton numbers are guesses
+pub fn unknown_particles(root: &Anomaly) -> Vec<Id> {
+    return anomalies_depth_first(root, &Path::default())
+        .filter(|(_, a)| a.anomaly.is_empty() && a.component.len() == 1)
+        .filter(|(_, a)| species_of(a).is_none())
+        .map(|(_, a)| a.id)
+        .collect();
+}
+
+pub fn quanta_line(q: &Quanta) -> String {
+    return format!(
+        "charge {} baryon {} lepton {} {} {} color {} {}",
+        q.charge, q.baryon, q.lepton[0], q.lepton[1], q.lepton[2], q.color[0], q.color[1]
+    );
+}
+
+// one line per broken event: the clock, the event and every law with its sums
+pub fn report_line(r: &Report) -> String {
+    let mut s = format!("not conserved at {:e} s by {}:", r.clock, r.event);
+    for v in &r.violation {
+        s = format!("{} {} {} -> {}", s, v.law, v.before, v.after);
+    }
+    return s;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, vacuum};
+    use crate::arena::{establish, insert_by_id, move_by_id, remove_by_id};
+    use crate::decay::{make_species, species_named};
+
+    fn made(name: &str) -> Anomaly {
+        let k = species_named(name).unwrap();
+        return make_species(k, [0.0; 3], [0.0; 3], SPECIES[k].mass, 0.0, true);
+    }
+
+    fn validated(name: &[&str]) -> (Anomaly, Vec<Id>) {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        let id = name
+            .iter()
+            .map(|n| add_particle_by(&mut root, top, made(n)).unwrap())
+            .collect();
+        root.validator = vec![validator_of(&root)];
+        return (root, id);
+    }
+
+    #[test]
+    fn a_lone_change_is_reported_with_the_laws_it_broke() {
+        let (mut root, id) = validated(&["mu-", "e+"]);
+        remove_by_id(&mut root, id[0]);
+
+        let v = &root.validator[0];
+        assert_eq!(v.checked, 1);
+        assert_eq!(v.report.len(), 1);
+        assert_eq!(
+            v.report[0].event,
+            format!("removing particle {}", id[0].index)
+        );
+        let law: Vec<&str> = v.report[0].violation.iter().map(|x| x.law).collect();
+        assert_eq!(law, vec!["charge", "muon number"]);
+    }
+
+    #[test]
+    fn an_event_is_checked_as_a_whole() {
+        let (mut root, id) = validated(&["mu-"]);
+        let top = root.id;
+        begin(&mut root);
+        remove_by_id(&mut root, id[0]);
+        for n in ["e-", "anti-nu_e", "nu_mu"] {
+            insert_by_id(&mut root, top, made(n));
+        }
+        end(&mut root, "decay");
+        assert!(root.validator[0].report.is_empty());
+
+        begin(&mut root);
+        insert_by_id(&mut root, top, made("u"));
+        end(&mut root, "stray quark");
+        let v = &root.validator[0];
+        assert_eq!(v.checked, 2);
+        assert_eq!(v.report.len(), 1);
+        assert_eq!(v.report[0].event, "stray quark");
+        assert_eq!(v.total, quanta(&root));
+    }
+
+    #[test]
+    fn moves_keep_every_sum() {
+        let (mut root, id) = validated(&["e-", "e+"]);
+        let top = root.id;
+        let group = add_particle_by(&mut root, top, vacuum()).unwrap();
+        for p in id {
+            move_by_id(&mut root, p, group);
+        }
+        assert!(root.validator[0].report.is_empty());
+        assert_eq!(root.validator[0].total, quanta(&root));
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use crate::anomaly::Anomaly;
+use crate::arena::Id;
+use crate::columns::{accelerate_column, separation, shortest, Columns};
+use crate::f64_3::{cross_product, dot_product, mltply_f64_3, sbtr_f64_3, vector_length};
+use crate::query::{anomalies_depth_first, Path};
+
+// ties between components anywhere below the anomaly holding them:
+//
+// bond     - a harmonic spring, U = k (d - length)^2 / 2
+// distance - a fixed length, kept by shake on positions and rattle on velocities
+// angle    - a harmonic spring on the angle at the middle component
+//
+// lengths in planck lengths, angles in radians, stiffness in MeV per planck
+// length squared or MeV per radian squared; ties to missing components are
+// skipped, in a periodic box the tied components see each other's nearest image
+
+pub static SHAKE_TOLERANCE: f64 = 1e-10;
+pub static SHAKE_ITERATIONS: u32 = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Constraint {
+    Bond {
+        a: Id,
+        b: Id,
+        length: f64,
+        stiffness: f64,
+    },
+    Distance {
+        a: Id,
+        b: Id,
+        length: f64,
+    },
+    Angle {
+        a: Id,
+        vertex: Id,
+        b: Id,
+        angle: f64,
+        stiffness: f64,
+    },
+}
+
+pub fn constraints_of(root: &Anomaly) -> Vec<Constraint> {
+    return anomalies_depth_first(root, &Path::default())
+        .flat_map(|(_, a)| a.constraint.iter().copied())
+        .collect();
+}
+
+// the components a constraint ties together
+pub fn tied(c: &Constraint) -> Vec<Id> {
+    return match *c {
+        Constraint::Bond { a, b, .. } | Constraint::Distance { a, b, .. } => vec![a, b],
+        Constraint::Angle { a, vertex, b, .. } => vec![a, vertex, b],
+    };
+}
+
+fn index_of(columns: &Columns) -> HashMap<Id, usize> {
+    return (0..columns.len()).map(|k| (columns.id[k], k)).collect();
+}
+
+// the springs, as a kick before the columns are stepped
+pub fn constraint_forces(constraint: &Vec<Constraint>, columns: &mut Columns, time: f64) {
+    let index = index_of(columns);
+
+    for c in constraint {
+        match *c {
+            Constraint::Bond {
+                a,
+                b,
+                length,
+                stiffness,
+            } => {
+                let (i, j) = match (index.get(&a), index.get(&b)) {
+                    (Some(i), Some(j)) => (*i, *j),
+                    _ => continue,
+                };
+                let r = separation(columns, j, i);
+                let d = vector_length(r);
+                if d == 0.0 {
+                    continue;
+                }
+                let f = mltply_f64_3(r, -stiffness * (d - length) / d);
+                accelerate_column(columns, j, f, time);
+                accelerate_column(columns, i, mltply_f64_3(f, -1.0), time);
+            }
+            Constraint::Angle {
+                a,
+                vertex,
+                b,
+                angle,
+                stiffness,
+            } => {
+                let (i, v, j) = match (index.get(&a), index.get(&vertex), index.get(&b)) {
+                    (Some(i), Some(v), Some(j)) => (*i, *v, *j),
+                    _ => continue,
+                };
+                let (fa, fb) = angle_forces(
+                    separation(columns, i, v),
+                    separation(columns, j, v),
+                    angle,
+                    stiffness,
+                );
+                accelerate_column(columns, i, fa, time);
+                accelerate_column(columns, j, fb, time);
+                let fv = mltply_f64_3([fa[0] + fb[0], fa[1] + fb[1], fa[2] + fb[2]], -1.0);
+                accelerate_column(columns, v, fv, time);
+            }
+            Constraint::Distance { .. } => {}
+        }
+    }
+}
+
+// forces on the two arm ends from -dU/dtheta, the vertex takes the rest
+fn angle_forces(u: [f64; 3], w: [f64; 3], angle: f64, stiffness: f64) -> ([f64; 3], [f64; 3]) {
+    let (lu, lw) = (vector_length(u), vector_length(w));
+    if lu == 0.0 || lw == 0.0 {
+        return ([0.0; 3], [0.0; 3]);
+    }
+    // straight angles have no direction to bend in
+    let sin = vector_length(cross_product(u, w)) / (lu * lw);
+    if sin < 1e-12 {
+        return ([0.0; 3], [0.0; 3]);
+    }
+    let cos = (dot_product(u, w) / (lu * lw)).clamp(-1.0, 1.0);
+    let torque = -stiffness * (cos.acos() - angle);
+
+    // d theta / d a = -(w^ - cos u^) / (|u| sin), likewise for b
+    let ua = mltply_f64_3(u, 1.0 / lu);
+    let wa = mltply_f64_3(w, 1.0 / lw);
+    let da = mltply_f64_3(sbtr_f64_3(wa, mltply_f64_3(ua, cos)), -1.0 / (lu * sin));
+    let db = mltply_f64_3(sbtr_f64_3(ua, mltply_f64_3(wa, cos)), -1.0 / (lw * sin));
+
+    return (mltply_f64_3(da, torque), mltply_f64_3(db, torque));
+}
+
+// shake: moves the tied pairs back onto their lengths along where they were tied
+// before the step, the velocities take the same correction; before holds the
+// positions of the columns as they were gathered
+pub fn shake(
+    constraint: &Vec<Constraint>,
+    before: &Vec<[f64; 3]>,
+    columns: &mut Columns,
+    time: f64,
+) {
+    let index = index_of(columns);
+    let tie = ties(constraint, &index);
+    if tie.is_empty() || time <= 0.0 {
+        return;
+    }
+
+    for _ in 0..SHAKE_ITERATIONS {
+        let mut done = true;
+        for (i, j, length) in &tie {
+            let (i, j) = (*i, *j);
+            let (wi, wj) = (weight(columns, i), weight(columns, j));
+            if wi + wj == 0.0 {
+                continue;
+            }
+            let r = separation(columns, i, j);
+            let old = shortest(columns, sbtr_f64_3(before[i], before[j]));
+            let miss = length * length - dot_product(r, r);
+            if miss.abs() <= SHAKE_TOLERANCE * length * length {
+                continue;
+            }
+            done = false;
+
+            let along = dot_product(r, old);
+            if along == 0.0 {
+                continue;
+            }
+            let g = miss / (2.0 * (wi + wj) * along);
+            shift(columns, i, mltply_f64_3(old, g * wi), time);
+            shift(columns, j, mltply_f64_3(old, -g * wj), time);
+        }
+        if done {
+            break;
+        }
+    }
+}
+
+// rattle: takes the velocity along each tie out, so the lengths stay put
+pub fn rattle(constraint: &Vec<Constraint>, columns: &mut Columns) {
+    let index = index_of(columns);
+    let tie = ties(constraint, &index);
+
+    for _ in 0..SHAKE_ITERATIONS {
+        let mut done = true;
+        for (i, j, _) in &tie {
+            let (i, j) = (*i, *j);
+            let (wi, wj) = (weight(columns, i), weight(columns, j));
+            let r = separation(columns, i, j);
+            let r2 = dot_product(r, r);
+            if wi + wj == 0.0 || r2 == 0.0 {
+                continue;
+            }
+            let v = sbtr_f64_3(columns.inertia(i), columns.inertia(j));
+            let along = dot_product(r, v);
+            if along.abs() <= SHAKE_TOLERANCE * vector_length(v) * r2.sqrt() {
+                continue;
+            }
+            done = false;
+
+            let k = along / ((wi + wj) * r2);
+            kick(columns, i, mltply_f64_3(r, -k * wi));
+            kick(columns, j, mltply_f64_3(r, k * wj));
+        }
+        if done {
+            break;
+        }
+    }
+}
+
+fn ties(constraint: &Vec<Constraint>, index: &HashMap<Id, usize>) -> Vec<(usize, usize, f64)> {
+    return constraint
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Distance { a, b, length } => match (index.get(a), index.get(b)) {
+                (Some(i), Some(j)) if i != j => Some((*i, *j, *length)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+}
+
+fn weight(columns: &Columns, k: usize) -> f64 {
+    return if columns.mass[k] > 0.0 {
+        1.0 / columns.mass[k]
+    } else {
+        0.0
+    };
+}
+
+fn shift(columns: &mut Columns, k: usize, d: [f64; 3], time: f64) {
+    columns.px[k] += d[0];
+    columns.py[k] += d[1];
+    columns.pz[k] += d[2];
+    kick(columns, k, mltply_f64_3(d, 1.0 / time));
+}
+
+fn kick(columns: &mut Columns, k: usize, dv: [f64; 3]) {
+    columns.ix[k] += dv[0];
+    columns.iy[k] += dv[1];
+    columns.iz[k] += dv[2];
+}
+
+// a tie as written in scenario files, particles numbered in the order they are made:
+//
+//   bond 0 1 length stiffness
+//   distance 0 1 length
+//   angle 0 1 2 degrees stiffness
+//
+// the first component of each particle is tied
+pub fn parse_constraint(text: &str, particle: &Vec<Id>) -> Result<Constraint, String> {
+    let word: Vec<&str> = text.split_whitespace().collect();
+    if word.is_empty() {
+        return Err("constraint: empty".to_string());
+    }
+    let v = word[1..]
+        .iter()
+        .map(|w| {
+            w.parse::<f64>()
+                .map_err(|_| format!("{}: {} is not a number", text, w))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    let id = |k: f64| {
+        particle
+            .get(k as usize)
+            .copied()
+            .filter(|_| k >= 0.0)
+            .ok_or_else(|| format!("{}: there is no particle {}", text, k))
+    };
+
+    return match (word[0], v.len()) {
+        ("bond", 4) => Ok(Constraint::Bond {
+            a: id(v[0])?,
+            b: id(v[1])?,
+            length: v[2],
+            stiffness: v[3],
+        }),
+        ("distance", 3) => Ok(Constraint::Distance {
+            a: id(v[0])?,
+            b: id(v[1])?,
+            length: v[2],
+        }),
+        ("angle", 5) => Ok(Constraint::Angle {
+            a: id(v[0])?,
+            vertex: id(v[1])?,
+            b: id(v[2])?,
+            angle: v[3].to_radians(),
+            stiffness: v[4],
+        }),
+        _ => Err(format!("{}: {} with {} numbers", text, word[0], v.len())),
+    };
+}
@@ -0,0 +1,974 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::anomaly::{
+    component_inertia, component_position, component_property, has_component_property, particle,
+    photon, Anomaly, Property, CR, EC, IN0, IN1, IN2, KN, LS_F64, MS, SO0, SO1, SO2, SP,
+};
+use crate::arena::{anomaly_by_id, insert_by_id, remove_by_id, Id};
+use crate::conservation::{begin, end};
+use crate::f64_3::{dot_product, mltply_f64_3, vector_length};
+use crate::force::share_force;
+
+// unstable particles decay on their own: every step a particle of a species
+// with a lifetime decays with probability 1 - exp(-dt / (gamma * lifetime)),
+// picks a channel by its branching ratio and is replaced, below the same
+// parent, by daughters that share its four momentum. the daughters are
+// spread over the phase space in the rest frame of the parent (raubold-lynch)
+// and boosted with its velocity, quarks hand their color down
+//
+// a particle is an anomaly of one component; its species is the KN property
+// when it has one, else the species with its mass, charge and color. members
+// of rigid composites are left alone. masses in MeV, lifetimes in seconds
+
+pub struct Channel {
+    pub ratio: f64,
+    pub daughter: &'static [&'static str],
+}
+
+pub struct Species {
+    pub name: &'static str,
+    pub mass: f64,
+    pub charge: f64,
+    // 1 for quarks, -1 for antiquarks
+    pub color: i8,
+    pub baryon: f64,
+    // electron, muon and tau numbers
+    pub lepton: [i8; 3],
+    pub spin: f64,
+    // 0 for stable
+    pub lifetime: f64,
+    pub channel: &'static [Channel],
+}
+
+const STABLE: &[Channel] = &[];
+
+pub static SPECIES: [Species; 28] = [
+    Species {
+        name: "gamma",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 0],
+        spin: 1.0,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "nu_e",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [1, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-nu_e",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [-1, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "nu_mu",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 1, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-nu_mu",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, -1, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "nu_tau",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 1],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-nu_tau",
+        mass: 0.0,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, -1],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "e-",
+        mass: 0.511,
+        charge: -1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [1, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "e+",
+        mass: 0.511,
+        charge: 1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [-1, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "mu-",
+        mass: 105.658,
+        charge: -1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 1, 0],
+        spin: 0.5,
+        lifetime: 2.197e-6,
+        channel: &[Channel {
+            ratio: 1.0,
+            daughter: &["e-", "anti-nu_e", "nu_mu"],
+        }],
+    },
+    Species {
+        name: "mu+",
+        mass: 105.658,
+        charge: 1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, -1, 0],
+        spin: 0.5,
+        lifetime: 2.197e-6,
+        channel: &[Channel {
+            ratio: 1.0,
+            daughter: &["e+", "nu_e", "anti-nu_mu"],
+        }],
+    },
+    Species {
+        name: "tau-",
+        mass: 1776.86,
+        charge: -1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 1],
+        spin: 0.5,
+        lifetime: 2.903e-13,
+        channel: &[
+            Channel {
+                ratio: 0.1782,
+                daughter: &["e-", "anti-nu_e", "nu_tau"],
+            },
+            Channel {
+                ratio: 0.1739,
+                daughter: &["mu-", "anti-nu_mu", "nu_tau"],
+            },
+            // the hadronic channels, all of them as one pion
+            Channel {
+                ratio: 0.6479,
+                daughter: &["pi-", "nu_tau"],
+            },
+        ],
+    },
+    Species {
+        name: "tau+",
+        mass: 1776.86,
+        charge: 1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, -1],
+        spin: 0.5,
+        lifetime: 2.903e-13,
+        channel: &[
+            Channel {
+                ratio: 0.1782,
+                daughter: &["e+", "nu_e", "anti-nu_tau"],
+            },
+            Channel {
+                ratio: 0.1739,
+                daughter: &["mu+", "nu_mu", "anti-nu_tau"],
+            },
+            Channel {
+                ratio: 0.6479,
+                daughter: &["pi+", "anti-nu_tau"],
+            },
+        ],
+    },
+    Species {
+        name: "pi0",
+        mass: 134.977,
+        charge: 0.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 0],
+        spin: 0.0,
+        lifetime: 8.43e-17,
+        channel: &[
+            Channel {
+                ratio: 0.98823,
+                daughter: &["gamma", "gamma"],
+            },
+            Channel {
+                ratio: 0.01174,
+                daughter: &["e+", "e-", "gamma"],
+            },
+        ],
+    },
+    Species {
+        name: "pi+",
+        mass: 139.570,
+        charge: 1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 0],
+        spin: 0.0,
+        lifetime: 2.603e-8,
+        channel: &[
+            Channel {
+                ratio: 0.999877,
+                daughter: &["mu+", "nu_mu"],
+            },
+            Channel {
+                ratio: 0.000123,
+                daughter: &["e+", "nu_e"],
+            },
+        ],
+    },
+    Species {
+        name: "pi-",
+        mass: 139.570,
+        charge: -1.0,
+        color: 0,
+        baryon: 0.0,
+        lepton: [0, 0, 0],
+        spin: 0.0,
+        lifetime: 2.603e-8,
+        channel: &[
+            Channel {
+                ratio: 0.999877,
+                daughter: &["mu-", "anti-nu_mu"],
+            },
+            Channel {
+                ratio: 0.000123,
+                daughter: &["e-", "anti-nu_e"],
+            },
+        ],
+    },
+    // q takes its quark masses from here; the heavy ones decay through a
+    // virtual w, taken to leptons only
+    Species {
+        name: "u",
+        mass: 2.2,
+        charge: 2.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "d",
+        mass: 4.7,
+        charge: -1.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "s",
+        mass: 96.0,
+        charge: -1.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "c",
+        mass: 1280.0,
+        charge: 2.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 1.04e-12,
+        channel: &[
+            Channel {
+                ratio: 0.5,
+                daughter: &["s", "e+", "nu_e"],
+            },
+            Channel {
+                ratio: 0.5,
+                daughter: &["s", "mu+", "nu_mu"],
+            },
+        ],
+    },
+    Species {
+        name: "b",
+        mass: 4180.0,
+        charge: -1.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 1.52e-12,
+        channel: &[
+            Channel {
+                ratio: 0.5,
+                daughter: &["c", "e-", "anti-nu_e"],
+            },
+            Channel {
+                ratio: 0.5,
+                daughter: &["c", "mu-", "anti-nu_mu"],
+            },
+        ],
+    },
+    Species {
+        name: "t",
+        mass: 173100.0,
+        charge: 2.0 / 3.0,
+        color: 1,
+        baryon: 1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 5e-25,
+        channel: &[
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["b", "e+", "nu_e"],
+            },
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["b", "mu+", "nu_mu"],
+            },
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["b", "tau+", "nu_tau"],
+            },
+        ],
+    },
+    Species {
+        name: "anti-u",
+        mass: 2.2,
+        charge: -2.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-d",
+        mass: 4.7,
+        charge: 1.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-s",
+        mass: 96.0,
+        charge: 1.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 0.0,
+        channel: STABLE,
+    },
+    Species {
+        name: "anti-c",
+        mass: 1280.0,
+        charge: -2.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 1.04e-12,
+        channel: &[
+            Channel {
+                ratio: 0.5,
+                daughter: &["anti-s", "e-", "anti-nu_e"],
+            },
+            Channel {
+                ratio: 0.5,
+                daughter: &["anti-s", "mu-", "anti-nu_mu"],
+            },
+        ],
+    },
+    Species {
+        name: "anti-b",
+        mass: 4180.0,
+        charge: 1.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 1.52e-12,
+        channel: &[
+            Channel {
+                ratio: 0.5,
+                daughter: &["anti-c", "e+", "nu_e"],
+            },
+            Channel {
+                ratio: 0.5,
+                daughter: &["anti-c", "mu+", "nu_mu"],
+            },
+        ],
+    },
+    Species {
+        name: "anti-t",
+        mass: 173100.0,
+        charge: -2.0 / 3.0,
+        color: -1,
+        baryon: -1.0 / 3.0,
+        lepton: [0, 0, 0],
+        spin: 0.5,
+        lifetime: 5e-25,
+        channel: &[
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["anti-b", "e-", "anti-nu_e"],
+            },
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["anti-b", "mu-", "anti-nu_mu"],
+            },
+            Channel {
+                ratio: 1.0 / 3.0,
+                daughter: &["anti-b", "tau-", "anti-nu_tau"],
+            },
+        ],
+    },
+];
+
+pub struct Event {
+    // s, the clock of the root
+    pub clock: f64,
+    pub parent: Id,
+    pub species: &'static str,
+    pub daughter: Vec<(Id, &'static str)>,
+    pub position: [f64; 3],
+}
+
+pub struct Decay {
+    // decays not yet taken into a snapshot
+    pub event: Vec<Event>,
+    rng: StdRng,
+}
+
+pub fn decay_base(seed: u64) -> Decay {
+    return Decay {
+        event: vec![],
+        rng: StdRng::seed_from_u64(seed),
+    };
+}
+
+pub fn species_named(name: &str) -> Option<usize> {
+    return SPECIES.iter().position(|s| s.name == name);
+}
+
+pub fn species_of(anom: &Anomaly) -> Option<usize> {
+    if !anom.anomaly.is_empty() || anom.component.len() != 1 {
+        return None;
+    }
+    let c = &anom.component[0];
+    if has_component_property(c, KN) {
+        return Some(component_property(c, KN) as usize).filter(|k| *k < SPECIES.len());
+    }
+    if !has_component_property(c, MS) {
+        return None;
+    }
+    let mass = component_property(c, MS);
+    let charge = if has_component_property(c, EC) {
+        component_property(c, EC)
+    } else {
+        0.0
+    };
+    let color = match has_component_property(c, CR) {
+        true if component_property(c, CR) >= 3.0 => -1,
+        true => 1,
+        false => 0,
+    };
+    return SPECIES.iter().position(|s| {
+        (s.mass - mass).abs() <= 1e-6 * s.mass
+            && (s.charge - charge).abs() < 1e-9
+            && s.color == color
+    });
+}
+
+// a particle of the species, colored like the one it came from
+pub fn make_species(
+    k: usize,
+    position: [f64; 3],
+    inertia: [f64; 3],
+    energy: f64,
+    color: f64,
+    clock: bool,
+) -> Anomaly {
+    let s = &SPECIES[k];
+    let mut p = if s.mass == 0.0 {
+        photon(position, inertia, energy)
+    } else {
+        let mut property = vec![
+            Property {
+                name: MS,
+                value: s.mass,
+            },
+            Property {
+                name: EC,
+                value: s.charge,
+            },
+            Property {
+                name: IN0,
+                value: inertia[0],
+            },
+            Property {
+                name: IN1,
+                value: inertia[1],
+            },
+            Property {
+                name: IN2,
+                value: inertia[2],
+            },
+        ];
+        if s.spin != 0.0 {
+            property.extend([
+                Property {
+                    name: SP,
+                    value: if clock { s.spin } else { -s.spin },
+                },
+                Property {
+                    name: SO0,
+                    value: 0.0,
+                },
+                Property {
+                    name: SO1,
+                    value: 0.0,
+                },
+                Property {
+                    name: SO2,
+                    value: 1.0,
+                },
+            ]);
+        }
+        if s.color != 0 {
+            property.push(Property {
+                name: CR,
+                value: color,
+            });
+        }
+        particle(position, property)
+    };
+    p.component[0].property.push(Property {
+        name: KN,
+        value: k as f64,
+    });
+    return p;
+}
+
+// momentum of either of two bodies of masses a and b in the rest frame of m
+fn two_body(m: f64, a: f64, b: f64) -> f64 {
+    let x = (m * m - (a + b).powi(2)) * (m * m - (a - b).powi(2));
+    return if x > 0.0 { x.sqrt() / (2.0 * m) } else { 0.0 };
+}
+
+fn isotropic(rng: &mut StdRng) -> [f64; 3] {
+    let z: f64 = rng.gen_range(-1.0..=1.0);
+    let phi: f64 = rng.gen_range(0.0..std::f64::consts::TAU);
+    let r = (1.0 - z * z).sqrt();
+    return [r * phi.cos(), r * phi.sin(), z];
+}
+
+// nothing massive reaches the speed of light, however the steps clamp it
+pub fn beta_of(velocity: [f64; 3]) -> [f64; 3] {
+    let beta = mltply_f64_3(velocity, 1.0 / LS_F64);
+    let b = vector_length(beta);
+    if b > 1.0 - 1e-9 {
+        return mltply_f64_3(beta, (1.0 - 1e-9) / b);
+    }
+    return beta;
+}
+
+// energy and momentum, MeV, of something moving with beta given in its rest frame
+pub fn boost(e: f64, p: [f64; 3], beta: [f64; 3]) -> (f64, [f64; 3]) {
+    let b2 = dot_product(beta, beta);
+    if b2 == 0.0 {
+        return (e, p);
+    }
+    let gamma = 1.0 / (1.0 - b2).sqrt();
+    let bp = dot_product(beta, p);
+    let along = (gamma - 1.0) * bp / b2 + gamma * e;
+    return (
+        gamma * (e + bp),
+        [
+            p[0] + along * beta[0],
+            p[1] + along * beta[1],
+            p[2] + along * beta[2],
+        ],
+    );
+}
+
+// energies and momenta of the daughters in the rest frame of mass m, weighted
+// by the product of the two body momenta of every split and unweighted by rejection
+pub fn phase_space(m: f64, mass: &Vec<f64>, rng: &mut StdRng) -> Vec<(f64, [f64; 3])> {
+    let n = mass.len();
+    if n < 2 {
+        return vec![(m, [0.0, 0.0, 0.0]); n];
+    }
+    let free = m - mass.iter().sum::<f64>();
+    let below: Vec<f64> = (0..n).map(|k| mass[..=k].iter().sum()).collect();
+
+    // the largest the weight gets, every split taking all the free energy
+    let mut most = 1.0;
+    for k in 1..n {
+        most *= two_body(below[k] + free, below[k - 1], mass[k]);
+    }
+
+    let mut split = vec![0.0; n];
+    for _ in 0..1000 {
+        let mut r: Vec<f64> = (0..n - 2).map(|_| rng.gen_range(0.0..1.0)).collect();
+        r.sort_by(|a, b| a.total_cmp(b));
+        for k in 0..n {
+            let x = match k {
+                0 => 0.0,
+                k if k == n - 1 => 1.0,
+                k => r[k - 1],
+            };
+            split[k] = below[k] + x * free;
+        }
+        let weight: f64 = (1..n)
+            .map(|k| two_body(split[k], split[k - 1], mass[k]))
+            .product();
+        if most <= 0.0 || rng.gen_range(0.0..1.0) * most <= weight {
+            break;
+        }
+    }
+
+    // each split sends daughter k one way and everything before it the other
+    let mut ret = vec![(mass[0], [0.0, 0.0, 0.0])];
+    for k in 1..n {
+        let p = mltply_f64_3(isotropic(rng), two_body(split[k], split[k - 1], mass[k]));
+        let rest = (split[k - 1].powi(2) + dot_product(p, p)).sqrt();
+        if k == 1 {
+            // the first daughter alone, which need not have a rest frame
+            ret[0] = (rest, mltply_f64_3(p, -1.0));
+        } else {
+            let beta = mltply_f64_3(p, -1.0 / rest);
+            for d in ret.iter_mut() {
+                *d = boost(d.0, d.1, beta);
+            }
+        }
+        ret.push(((mass[k].powi(2) + dot_product(p, p)).sqrt(), p));
+    }
+    return ret;
+}
+
+// every particle that may decay, with its parent, not counting members of rigid composites
+fn unstable(anom: &Anomaly, ret: &mut Vec<(Id, Id, usize)>) {
+    if !anom.rigid.is_empty() {
+        return;
+    }
+    for a in &anom.anomaly {
+        match species_of(a) {
+            Some(k) if SPECIES[k].lifetime > 0.0 => ret.push((a.id, anom.id, k)),
+            _ => unstable(a, ret),
+        }
+    }
+}
+
+fn channel_of(k: usize, rng: &mut StdRng) -> Option<&'static Channel> {
+    let channel = SPECIES[k].channel;
+    let total: f64 = channel.iter().map(|c| c.ratio).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut x = rng.gen_range(0.0..total);
+    for c in channel {
+        if x < c.ratio {
+            return Some(c);
+        }
+        x -= c.ratio;
+    }
+    return channel.last();
+}
+
+// of decaying within the proper time, 1 - exp(-t / lifetime) would round a
+// planck time step to nothing
+fn chance(time: f64, lifetime: f64) -> f64 {
+    return -(-time / lifetime).exp_m1();
+}
+
+pub fn decay_step(d: &mut Decay, root: &mut Anomaly, time: f64) {
+    let mut candidate = vec![];
+    unstable(root, &mut candidate);
+
+    for (id, parent, k) in candidate {
+        let (position, velocity) = match anomaly_by_id(root, id) {
+            Some(a) => (
+                component_position(&a.component[0]),
+                component_inertia(&a.component[0]),
+            ),
+            None => continue,
+        };
+        let beta = beta_of(velocity);
+        let gamma = 1.0 / (1.0 - dot_product(beta, beta)).sqrt();
+        if d.rng.gen_range(0.0..1.0) >= chance(time / gamma, SPECIES[k].lifetime) {
+            continue;
+        }
+
+        let channel = match channel_of(k, &mut d.rng) {
+            Some(c) => c,
+            None => continue,
+        };
+        let kind: Vec<usize> = channel
+            .daughter
+            .iter()
+            .filter_map(|n| species_named(n))
+            .collect();
+        let mass: Vec<f64> = kind.iter().map(|j| SPECIES[*j].mass).collect();
+        if kind.len() != channel.daughter.len() || mass.iter().sum::<f64>() > SPECIES[k].mass {
+            continue;
+        }
+
+        begin(root);
+        let old = match remove_by_id(root, id) {
+            Some(a) => a,
+            None => {
+                end(root, "no decay");
+                continue;
+            }
+        };
+        let c = &old.component[0];
+        let color = if has_component_property(c, CR) {
+            component_property(c, CR)
+        } else {
+            0.0
+        };
+
+        let mut daughter = vec![];
+        for (j, (e, p)) in kind
+            .iter()
+            .zip(phase_space(SPECIES[k].mass, &mass, &mut d.rng))
+        {
+            let (e, p) = boost(e, p, beta);
+            let mut a = make_species(
+                *j,
+                position,
+                mltply_f64_3(p, LS_F64 / e),
+                e,
+                color,
+                d.rng.gen_range(0..2) == 0,
+            );
+            a.force = old.force.iter().map(share_force).collect();
+            if let Some(n) = insert_by_id(root, parent, a) {
+                daughter.push((n, SPECIES[*j].name));
+            }
+        }
+
+        let event = Event {
+            clock: root.clock,
+            parent: id,
+            species: SPECIES[k].name,
+            daughter,
+            position,
+        };
+        end(root, &event_line(&event));
+        d.event.push(event);
+    }
+}
+
+// one line per decay: the clock, the parent and the daughters
+pub fn event_line(e: &Event) -> String {
+    let daughter: Vec<&str> = e.daughter.iter().map(|(_, n)| *n).collect();
+    return format!(
+        "decay at {:e} s: {} {} -> {}",
+        e.clock,
+        e.species,
+        e.parent.index,
+        daughter.join(" ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, q, vacuum, TS_F64};
+    use crate::arena::establish;
+
+    #[test]
+    fn channels_keep_charge_baryon_lepton_numbers_and_color() {
+        for s in SPECIES.iter().filter(|s| s.lifetime > 0.0) {
+            // channel_of normalizes, the rarest channels may be left out
+            let ratio: f64 = s.channel.iter().map(|c| c.ratio).sum();
+            assert!(
+                (ratio - 1.0).abs() < 1e-3,
+                "{} ratios sum to {}",
+                s.name,
+                ratio
+            );
+            for c in s.channel {
+                let daughter: Vec<&Species> = c
+                    .daughter
+                    .iter()
+                    .map(|n| &SPECIES[species_named(n).unwrap()])
+                    .collect();
+                let charge: f64 = daughter.iter().map(|d| d.charge).sum();
+                let baryon: f64 = daughter.iter().map(|d| d.baryon).sum();
+                let color: i8 = daughter.iter().map(|d| d.color).sum();
+                let mass: f64 = daughter.iter().map(|d| d.mass).sum();
+                assert!(
+                    (charge - s.charge).abs() < 1e-9,
+                    "{} {:?}",
+                    s.name,
+                    c.daughter
+                );
+                assert!(
+                    (baryon - s.baryon).abs() < 1e-9,
+                    "{} {:?}",
+                    s.name,
+                    c.daughter
+                );
+                assert_eq!(color, s.color, "{} {:?}", s.name, c.daughter);
+                assert!(mass < s.mass, "{} {:?}", s.name, c.daughter);
+                for x in 0..3 {
+                    let lepton: i8 = daughter.iter().map(|d| d.lepton[x]).sum();
+                    assert_eq!(lepton, s.lepton[x], "{} {:?}", s.name, c.daughter);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn phase_space_closes_energy_and_momentum() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let beta = [0.3, -0.5, 0.2];
+        for s in SPECIES.iter().filter(|s| s.lifetime > 0.0) {
+            for c in s.channel {
+                let mass: Vec<f64> = c
+                    .daughter
+                    .iter()
+                    .map(|n| SPECIES[species_named(n).unwrap()].mass)
+                    .collect();
+                let (e0, p0) = boost(s.mass, [0.0; 3], beta);
+                let mut e = 0.0;
+                let mut p = [0.0; 3];
+                for (m, (de, dp)) in mass.iter().zip(phase_space(s.mass, &mass, &mut rng)) {
+                    assert!(de.is_finite() && dp.iter().all(|x| x.is_finite()));
+                    let invariant = (de * de - dot_product(dp, dp)).max(0.0).sqrt();
+                    assert!((invariant - m).abs() <= 1e-6 * s.mass);
+                    let (de, dp) = boost(de, dp, beta);
+                    e += de;
+                    p = [p[0] + dp[0], p[1] + dp[1], p[2] + dp[2]];
+                }
+                assert!((e - e0).abs() <= 1e-9 * e0, "{} {:?}", s.name, c.daughter);
+                for x in 0..3 {
+                    assert!(
+                        (p[x] - p0[x]).abs() <= 1e-9 * e0,
+                        "{} {:?}",
+                        s.name,
+                        c.daughter
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_quark_q_makes_is_its_species() {
+        let name = ["u", "d", "c", "s", "t", "b"];
+        for flavor in 0..6u8 {
+            for color in 0..6u8 {
+                let a = q([0.0; 3], [0.0; 3], true, flavor % 2 == 0, color, flavor);
+                let k = species_of(&a).unwrap();
+                let anti = if color >= 3 { "anti-" } else { "" };
+                assert_eq!(
+                    SPECIES[k].name,
+                    format!("{}{}", anti, name[flavor as usize])
+                );
+                assert_eq!(component_property(&a.component[0], MS), SPECIES[k].mass);
+                assert!((component_property(&a.component[0], EC) - SPECIES[k].charge).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn muons_and_taus_decay_into_a_channel() {
+        for name in ["mu-", "tau-"] {
+            let k = species_named(name).unwrap();
+            assert!(chance(TS_F64, SPECIES[k].lifetime) > 0.0);
+
+            let mut root = vacuum();
+            establish(&mut root);
+            let top = root.id;
+            let inertia = [0.5 * LS_F64, 0.0, 0.0];
+            add_particle_by(
+                &mut root,
+                top,
+                make_species(k, [0.0; 3], inertia, 0.0, 0.0, true),
+            );
+            let mut d = decay_base(11);
+            for _ in 0..100 {
+                decay_step(&mut d, &mut root, 10.0 * SPECIES[k].lifetime);
+                if !d.event.is_empty() {
+                    break;
+                }
+            }
+
+            assert_eq!(d.event.len(), 1, "{} did not decay", name);
+            assert_eq!(d.event[0].species, name);
+            let mut daughter: Vec<&str> = root
+                .anomaly
+                .iter()
+                .map(|a| SPECIES[species_of(a).unwrap()].name)
+                .collect();
+            daughter.sort();
+            assert!(
+                SPECIES[k].channel.iter().any(|c| {
+                    let mut expected = c.daughter.to_vec();
+                    expected.sort();
+                    expected == daughter
+                }),
+                "{} went to {:?}",
+                name,
+                daughter
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::{PI, TAU};
+
+use crate::f32_3::{dd_f32_3, find_orthogonal_f32_3, mltply_f32_3, nrmlz_f32_3};
+
+// a distribution spreads every point of a composition's space into the points
+// the component actually occupies, so one component can be an extended object
+
+pub trait Distribution: Send + Sync {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]>;
+    fn name(&self) -> &'static str;
+    fn parameters(&self) -> Vec<(&'static str, f64)>;
+
+    fn serialize(&self) -> String {
+        let mut s = self.name().to_string();
+        for (_, v) in self.parameters() {
+            s = format!("{} {}", s, v);
+        }
+        return s;
+    }
+}
+
+// offsets are small, they stay f32 while the points they spread are f64
+fn spread(space: &Vec<[f64; 3]>, offset: &Vec<[f32; 3]>) -> Vec<[f64; 3]> {
+    let mut ret = vec![];
+    for s in space {
+        for o in offset {
+            ret.push([s[0] + o[0] as f64, s[1] + o[1] as f64, s[2] + o[2] as f64]);
+        }
+    }
+    return ret;
+}
+
+// two directions orthogonal to the normal and to each other
+fn plane_of(normal: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let n = nrmlz_f32_3(normal);
+    let helper = if n[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let u = find_orthogonal_f32_3(n, helper);
+    let v = find_orthogonal_f32_3(n, u);
+    return (u, v);
+}
+
+pub struct Particular {}
+
+impl Distribution for Particular {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return space.clone();
+    }
+
+    fn name(&self) -> &'static str {
+        "particular"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![]
+    }
+}
+
+pub struct Shell {
+    radius: f32,
+    count: u32,
+    offset: Vec<[f32; 3]>,
+}
+
+// points spread evenly over a sphere along a fibonacci spiral
+pub fn shell(radius: f32, count: u32) -> Shell {
+    let mut offset = vec![];
+    let golden = PI * (3.0 - 5.0_f32.sqrt());
+    for i in 0..count {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+        let r = (1.0 - y * y).sqrt();
+        let t = golden * i as f32;
+        offset.push(mltply_f32_3([r * t.cos(), y, r * t.sin()], radius));
+    }
+
+    return Shell {
+        radius,
+        count,
+        offset,
+    };
+}
+
+impl Distribution for Shell {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return spread(space, &self.offset);
+    }
+
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![("radius", self.radius as f64), ("count", self.count as f64)]
+    }
+}
+
+pub struct Lattice {
+    spacing: f32,
+    count: [u32; 3],
+    offset: Vec<[f32; 3]>,
+}
+
+// a cubic grid centered on the point
+pub fn lattice(spacing: f32, count: [u32; 3]) -> Lattice {
+    let mut offset = vec![];
+    let half = mltply_f32_3(
+        [
+            count[0].max(1) as f32 - 1.0,
+            count[1].max(1) as f32 - 1.0,
+            count[2].max(1) as f32 - 1.0,
+        ],
+        spacing / 2.0,
+    );
+    for i in 0..count[0] {
+        for j in 0..count[1] {
+            for k in 0..count[2] {
+                offset.push([
+                    i as f32 * spacing - half[0],
+                    j as f32 * spacing - half[1],
+                    k as f32 * spacing - half[2],
+                ]);
+            }
+        }
+    }
+
+    return Lattice {
+        spacing,
+        count,
+        offset,
+    };
+}
+
+impl Distribution for Lattice {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return spread(space, &self.offset);
+    }
+
+    fn name(&self) -> &'static str {
+        "lattice"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("spacing", self.spacing as f64),
+            ("count0", self.count[0] as f64),
+            ("count1", self.count[1] as f64),
+            ("count2", self.count[2] as f64),
+        ]
+    }
+}
+
+pub struct Cloud {
+    sigma: f32,
+    count: u32,
+    seed: u64,
+    offset: Vec<[f32; 3]>,
+}
+
+// standard normal draws, box muller
+pub fn gaussian_draws(count: u32, seed: u64) -> Vec<[f32; 3]> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut normal = || {
+        let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+        let v: f32 = rng.gen_range(0.0..1.0);
+        (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+    };
+
+    return (0..count).map(|_| [normal(), normal(), normal()]).collect();
+}
+
+// gaussian cloud, sampled once so it keeps its shape between frames
+pub fn cloud(sigma: f32, count: u32, seed: u64) -> Cloud {
+    let offset = gaussian_draws(count, seed)
+        .into_iter()
+        .map(|d| mltply_f32_3(d, sigma))
+        .collect();
+
+    return Cloud {
+        sigma,
+        count,
+        seed,
+        offset,
+    };
+}
+
+impl Distribution for Cloud {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return spread(space, &self.offset);
+    }
+
+    fn name(&self) -> &'static str {
+        "cloud"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("sigma", self.sigma as f64),
+            ("count", self.count as f64),
+            ("seed", self.seed as f64),
+        ]
+    }
+}
+
+pub struct Ring {
+    radius: f32,
+    count: u32,
+    normal: [f32; 3],
+    offset: Vec<[f32; 3]>,
+}
+
+pub fn ring(radius: f32, count: u32, normal: [f32; 3]) -> Ring {
+    let (u, v) = plane_of(normal);
+    let mut offset = vec![];
+    for i in 0..count {
+        let t = TAU * i as f32 / count as f32;
+        offset.push(mltply_f32_3(
+            dd_f32_3(mltply_f32_3(u, t.cos()), mltply_f32_3(v, t.sin())),
+            radius,
+        ));
+    }
+
+    return Ring {
+        radius,
+        count,
+        normal,
+        offset,
+    };
+}
+
+impl Distribution for Ring {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return spread(space, &self.offset);
+    }
+
+    fn name(&self) -> &'static str {
+        "ring"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("radius", self.radius as f64),
+            ("count", self.count as f64),
+            ("normal0", self.normal[0] as f64),
+            ("normal1", self.normal[1] as f64),
+            ("normal2", self.normal[2] as f64),
+        ]
+    }
+}
+
+pub struct Helix {
+    radius: f32,
+    pitch: f32,
+    turns: f32,
+    count: u32,
+    axis: [f32; 3],
+    offset: Vec<[f32; 3]>,
+}
+
+// helix around the axis, centered on the point
+pub fn helix(radius: f32, pitch: f32, turns: f32, count: u32, axis: [f32; 3]) -> Helix {
+    let (u, v) = plane_of(axis);
+    let n = nrmlz_f32_3(axis);
+    let mut offset = vec![];
+    for i in 0..count {
+        let f = if count > 1 {
+            i as f32 / (count - 1) as f32
+        } else {
+            0.5
+        };
+        let t = TAU * turns * f;
+        let around = dd_f32_3(mltply_f32_3(u, t.cos()), mltply_f32_3(v, t.sin()));
+        offset.push(dd_f32_3(
+            mltply_f32_3(around, radius),
+            mltply_f32_3(n, pitch * turns * (f - 0.5)),
+        ));
+    }
+
+    return Helix {
+        radius,
+        pitch,
+        turns,
+        count,
+        axis,
+        offset,
+    };
+}
+
+impl Distribution for Helix {
+    fn distribute(&self, space: &Vec<[f64; 3]>) -> Vec<[f64; 3]> {
+        return spread(space, &self.offset);
+    }
+
+    fn name(&self) -> &'static str {
+        "helix"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("radius", self.radius as f64),
+            ("pitch", self.pitch as f64),
+            ("turns", self.turns as f64),
+            ("count", self.count as f64),
+            ("axis0", self.axis[0] as f64),
+            ("axis1", self.axis[1] as f64),
+            ("axis2", self.axis[2] as f64),
+        ]
+    }
+}
+
+// reads back what serialize wrote
+pub fn deserialize(text: &str) -> Result<Box<dyn Distribution>, String> {
+    let word: Vec<&str> = text.split_whitespace().collect();
+    if word.is_empty() {
+        return Err("empty distribution".to_string());
+    }
+    let v = word[1..]
+        .iter()
+        .map(|w| {
+            w.parse::<f64>()
+                .map_err(|_| format!("distribution {}: {} is not a number", word[0], w))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    let d: Box<dyn Distribution> = match (word[0], v.len()) {
+        ("particular", 0) => Box::new(Particular {}),
+        ("shell", 2) => Box::new(shell(v[0] as f32, v[1] as u32)),
+        ("lattice", 4) => Box::new(lattice(
+            v[0] as f32,
+            [v[1] as u32, v[2] as u32, v[3] as u32],
+        )),
+        ("cloud", 3) => Box::new(cloud(v[0] as f32, v[1] as u32, v[2] as u64)),
+        ("ring", 5) => Box::new(ring(
+            v[0] as f32,
+            v[1] as u32,
+            [v[2] as f32, v[3] as f32, v[4] as f32],
+        )),
+        ("helix", 7) => Box::new(helix(
+            v[0] as f32,
+            v[1] as f32,
+            v[2] as f32,
+            v[3] as u32,
+            [v[4] as f32, v[5] as f32, v[6] as f32],
+        )),
+        _ => return Err(format!("distribution {} with {} numbers", word[0], v.len())),
+    };
+
+    return Ok(d);
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use crate::anomaly::{progress, Anomaly, TS_F64};
+use crate::cluster::{clusters, energies, watch_step};
+use crate::columns::{central_laws, gather};
+use crate::monte_carlo::{gyration, sample_step, sampler_base};
+use crate::scenario::{apply_setting, parse_scenario, scenario_anomaly};
+use crate::simulation::events;
+use crate::thermostat::temperature;
+
+// the same scenario run headless over many seeds, for every point of the swept
+// settings; run k of every point gets seed + k, so the points differ only in
+// the settings. each run is reduced to a few diagnostics at its end and every
+// diagnostic is aggregated per point into one csv row:
+//
+//   electrons,coupling EC,diagnostic,runs,mean,variance,p5,p25,p50,p75,p95
+
+pub static DIAGNOSTICS: [&str; 7] = [
+    "kinetic",
+    "potential",
+    "energy",
+    "gyration",
+    "temperature",
+    "clusters",
+    "seconds",
+];
+
+pub struct Ensemble {
+    pub runs: u32,
+    pub steps: u64,
+    pub file: String,
+}
+
+pub struct Sweep {
+    pub setting: String,
+    pub value: Vec<f64>,
+}
+
+pub fn ensemble_base(runs: u32, steps: u64, file: &str) -> Ensemble {
+    return Ensemble {
+        runs: runs.max(1),
+        steps,
+        file: file.to_string(),
+    };
+}
+
+// every combination of swept values, the first sweep varying slowest
+pub fn sweep_points(sweep: &Vec<Sweep>) -> Vec<Vec<f64>> {
+    let mut points: Vec<Vec<f64>> = vec![vec![]];
+    for s in sweep {
+        points = points
+            .iter()
+            .flat_map(|p| {
+                s.value.iter().map(move |v| {
+                    let mut q = p.clone();
+                    q.push(*v);
+                    q
+                })
+            })
+            .collect();
+    }
+    return points;
+}
+
+// the diagnostics of one run, in the order of DIAGNOSTICS
+pub fn run(text: &str, point: &Vec<f64>, seed: u64) -> Result<Vec<f64>, String> {
+    let mut scenario = parse_scenario(text)?;
+    let setting: Vec<String> = scenario.sweep.iter().map(|s| s.setting.clone()).collect();
+    for (s, v) in setting.iter().zip(point) {
+        apply_setting(&mut scenario, s, *v)?;
+    }
+    scenario.seed = vec![seed];
+    let steps = scenario.ensemble.first().map_or(0, |e| e.steps);
+    let mut sampler = scenario
+        .sampler
+        .pop()
+        .map(|s| sampler_base(s.temperature, s.step, s.every, seed));
+    let mut watch = scenario.cluster.pop();
+
+    let start = Instant::now();
+    let mut anom = scenario_anomaly(&scenario)?;
+    for _ in 0..steps {
+        match &mut sampler {
+            Some(s) => sample_step(s, &mut anom),
+            None => progress(&mut anom, TS_F64),
+        }
+        if let Some(w) = &mut watch {
+            watch_step(w, &mut anom);
+        }
+        // headless, nobody reads the lines
+        events(&mut anom);
+    }
+
+    return Ok(diagnostics(
+        &anom,
+        watch.map_or(0.0, |w| w.linking),
+        start.elapsed().as_secs_f64(),
+    ));
+}
+
+fn diagnostics(anom: &Anomaly, linking: f64, seconds: f64) -> Vec<f64> {
+    let columns = gather(anom);
+    let laws = central_laws(&anom.force);
+    let all: Vec<usize> = (0..columns.len()).collect();
+    let (kinetic, potential) = energies(&columns, &laws, &all);
+    let found = if linking > 0.0 {
+        clusters(anom, linking).len()
+    } else {
+        0
+    };
+
+    return vec![
+        kinetic,
+        potential,
+        kinetic + potential,
+        gyration(&columns),
+        temperature(&columns),
+        found as f64,
+        seconds,
+    ];
+}
+
+// linear between the closest ranks
+pub fn percentile(sorted: &Vec<f64>, p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let x = p / 100.0 * (sorted.len() - 1) as f64;
+    let (low, high) = (x.floor() as usize, x.ceil() as usize);
+    return sorted[low] + (sorted[high] - sorted[low]) * (x - low as f64);
+}
+
+// mean, sample variance and the 5 25 50 75 95 percentiles
+pub fn aggregate(sample: &Vec<f64>) -> Vec<f64> {
+    let n = sample.len() as f64;
+    if n == 0.0 {
+        return vec![0.0; 7];
+    }
+    let mean = sample.iter().sum::<f64>() / n;
+    let variance = if n > 1.0 {
+        sample.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+    let mut sorted = sample.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let mut ret = vec![mean, variance];
+    for p in [5.0, 25.0, 50.0, 75.0, 95.0] {
+        ret.push(percentile(&sorted, p));
+    }
+    return ret;
+}
+
+// runs every seed of every point on as many threads as there are cores and
+// writes the csv named in the scenario's ensemble line
+pub fn run_ensemble(text: &str) -> Result<String, String> {
+    let scenario = parse_scenario(text)?;
+    let ensemble = scenario
+        .ensemble
+        .first()
+        .ok_or("the scenario has no ensemble line")?;
+    let points = sweep_points(&scenario.sweep);
+    let base = scenario.seed.first().copied().unwrap_or(0);
+    let runs = ensemble.runs as usize;
+
+    let job: Vec<(usize, u64)> = (0..points.len())
+        .flat_map(|p| (0..runs).map(move |k| (p, base + k as u64)))
+        .collect();
+    let next = AtomicUsize::new(0);
+    let result: Mutex<Vec<Vec<Vec<f64>>>> = Mutex::new(vec![vec![]; points.len()]);
+    let failure: Mutex<Vec<String>> = Mutex::new(vec![]);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+
+    thread::scope(|s| {
+        for _ in 0..workers.min(job.len()) {
+            s.spawn(|| loop {
+                let k = next.fetch_add(1, Ordering::Relaxed);
+                let (p, seed) = match job.get(k) {
+                    Some(j) => *j,
+                    None => return,
+                };
+                match run(text, &points[p], seed) {
+                    Ok(d) => {
+                        println!("run {} of {}: seed {} {:?}", k + 1, job.len(), seed, d);
+                        result.lock().unwrap()[p].push(d);
+                    }
+                    Err(e) => failure.lock().unwrap().push(e),
+                }
+            });
+        }
+    });
+
+    if let Some(e) = failure.into_inner().unwrap().first() {
+        return Err(e.clone());
+    }
+
+    let mut csv = String::new();
+    for s in &scenario.sweep {
+        csv += &format!("{},", s.setting);
+    }
+    csv += "diagnostic,runs,mean,variance,p5,p25,p50,p75,p95\n";
+    for (p, runs) in result.into_inner().unwrap().iter().enumerate() {
+        for (d, name) in DIAGNOSTICS.iter().enumerate() {
+            let sample: Vec<f64> = runs.iter().map(|r| r[d]).collect();
+            let mut row: Vec<String> = points[p].iter().map(|v| v.to_string()).collect();
+            row.push(name.to_string());
+            row.push(sample.len().to_string());
+            row.extend(aggregate(&sample).iter().map(|v| format!("{:e}", v)));
+            csv += &(row.join(",") + "\n");
+        }
+    }
+
+    fs::write(&ensemble.file, &csv).map_err(|e| format!("{}: {}", ensemble.file, e))?;
+    return Ok(ensemble.file.clone());
+}
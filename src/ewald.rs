@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+
+use crate::anomaly::Anomaly;
+use crate::arena::Id;
+use crate::cluster::find;
+use crate::columns::Columns;
+use crate::constraint::{tied, Constraint};
+use crate::f64_3::{dd_f64_3, mltply_f64_3, sbtr_f64_3};
+use crate::query::visit_anomalies_mut;
+use crate::wavepacket::erf;
+
+// a periodic box centered on the origin: composites leaving it come back on
+// the other side whole, the inverse square laws of mass reach the nearest image of
+// every other column and the electric ones every image, by ewald summation:
+//
+//   real space  - the charges screened by gaussians of width 1 / alpha,
+//                 short ranged, summed over nearest images within the cutoff
+//   reciprocal  - the screening gaussians, smooth, summed over wave vectors
+//                 up to kmax in every direction
+//
+// the tolerance is how small both parts are where they are cut off, alpha and
+// kmax follow from it with the cutoff at half the shortest side. the box is
+// neutralized by a uniform background, which moves nothing; ties and rigid
+// bodies measure to the nearest image, wavepacket smearing does not see the
+// images at all. sizes in planck lengths
+
+pub static EWALD_TOLERANCE: f64 = 1e-5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Periodic {
+    pub size: [f64; 3],
+    pub tolerance: f64,
+}
+
+pub fn periodic_base(size: [f64; 3], tolerance: f64) -> Periodic {
+    return Periodic {
+        size,
+        tolerance: if tolerance > 0.0 && tolerance < 1.0 {
+            tolerance
+        } else {
+            EWALD_TOLERANCE
+        },
+    };
+}
+
+pub struct Ewald {
+    pub size: [f64; 3],
+    pub alpha: f64,
+    pub cutoff: f64,
+    pub kmax: [i32; 3],
+    // every wave vector but 0, with 4 pi / V exp(-k^2 / 4 alpha^2) / k^2
+    wave: Vec<([f64; 3], f64)>,
+}
+
+pub fn ewald_base(p: &Periodic) -> Ewald {
+    let s = (-p.tolerance.ln()).sqrt();
+    let cutoff = 0.5 * p.size[0].min(p.size[1]).min(p.size[2]);
+    let alpha = s / cutoff;
+    let kmax = p.size.map(|l| (s * alpha * l / PI).ceil() as i32);
+    let volume = p.size[0] * p.size[1] * p.size[2];
+
+    let mut wave = vec![];
+    for x in -kmax[0]..=kmax[0] {
+        for y in -kmax[1]..=kmax[1] {
+            for z in -kmax[2]..=kmax[2] {
+                if x == 0 && y == 0 && z == 0 {
+                    continue;
+                }
+                let k = [
+                    2.0 * PI * x as f64 / p.size[0],
+                    2.0 * PI * y as f64 / p.size[1],
+                    2.0 * PI * z as f64 / p.size[2],
+                ];
+                let k2 = k[0] * k[0] + k[1] * k[1] + k[2] * k[2];
+                let c = 4.0 * PI / volume * (-k2 / (4.0 * alpha * alpha)).exp() / k2;
+                if c > 0.0 {
+                    wave.push((k, c));
+                }
+            }
+        }
+    }
+
+    return Ewald {
+        size: p.size,
+        alpha,
+        cutoff,
+        kmax,
+        wave,
+    };
+}
+
+// the shortest of the separations between the images
+pub fn nearest_image(d: [f64; 3], size: [f64; 3]) -> [f64; 3] {
+    return [
+        d[0] - size[0] * (d[0] / size[0]).round(),
+        d[1] - size[1] * (d[1] / size[1]).round(),
+        d[2] - size[2] * (d[2] / size[2]).round(),
+    ];
+}
+
+// moves every composite back into the box as a whole by its center: the
+// columns below one child of the root and everything tied to them go together,
+// rigid bodies among them with their centers, so no bond or body is cut
+pub fn wrap(
+    root: &mut Anomaly,
+    constraint: &Vec<Constraint>,
+    columns: &mut Columns,
+    size: [f64; 3],
+) {
+    let n = columns.len();
+    let index: HashMap<Id, usize> = (0..n).map(|k| (columns.id[k], k)).collect();
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut child: HashMap<usize, usize> = HashMap::new();
+    for k in 0..n {
+        if let Some(a) = columns.path[k].anomaly.first() {
+            let first = *child.entry(*a).or_insert(k);
+            join(&mut parent, first, k);
+        }
+    }
+    for c in constraint {
+        let k: Vec<usize> = tied(c)
+            .iter()
+            .filter_map(|id| index.get(id).copied())
+            .collect();
+        for w in k.windows(2) {
+            join(&mut parent, w[0], w[1]);
+        }
+    }
+
+    let mut group: HashMap<usize, Vec<usize>> = HashMap::new();
+    for k in 0..n {
+        let g = find(&mut parent, k);
+        group.entry(g).or_default().push(k);
+    }
+
+    for member in group.values() {
+        // the center from the first member, so a group across a side stays whole
+        let first = columns.position(member[0]);
+        let mass: f64 = member.iter().map(|k| columns.mass[*k]).sum();
+        let mut center = [0.0, 0.0, 0.0];
+        for k in member {
+            let w = if mass > 0.0 {
+                columns.mass[*k] / mass
+            } else {
+                1.0 / member.len() as f64
+            };
+            let d = nearest_image(sbtr_f64_3(columns.position(*k), first), size);
+            center = dd_f64_3(center, mltply_f64_3(d, w));
+        }
+        center = dd_f64_3(first, center);
+        let shift = sbtr_f64_3(nearest_image(center, size), center);
+        if shift == [0.0, 0.0, 0.0] {
+            continue;
+        }
+
+        for k in member {
+            columns.px[*k] += shift[0];
+            columns.py[*k] += shift[1];
+            columns.pz[*k] += shift[2];
+        }
+        let moved: HashSet<usize> = member
+            .iter()
+            .filter_map(|k| columns.path[*k].anomaly.first().copied())
+            .collect();
+        for a in moved {
+            visit_anomalies_mut(&mut root.anomaly[a], &mut |_, x| {
+                for body in x.rigid.iter_mut() {
+                    body.center = dd_f64_3(body.center, shift);
+                }
+            });
+        }
+    }
+}
+
+fn join(parent: &mut Vec<usize>, a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    parent[a] = b;
+}
+
+// the electric force on every column, MeV per planck length; strength is
+// sign * coupling * EP of the law, charges are the columns' own
+pub fn ewald_forces(columns: &Columns, e: &Ewald, strength: f64) -> Vec<[f64; 3]> {
+    let n = columns.len();
+    let q = &columns.charge;
+    let mut force = vec![[0.0, 0.0, 0.0]; n];
+
+    let screen = 2.0 * e.alpha / PI.sqrt();
+    for i in 0..n {
+        if q[i] == 0.0 {
+            continue;
+        }
+        for j in i + 1..n {
+            if q[j] == 0.0 {
+                continue;
+            }
+            let d = nearest_image(
+                [
+                    columns.px[i] - columns.px[j],
+                    columns.py[i] - columns.py[j],
+                    columns.pz[i] - columns.pz[j],
+                ],
+                e.size,
+            );
+            let r = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+            if r == 0.0 || r > e.cutoff {
+                continue;
+            }
+            let ar = e.alpha * r;
+            let w = strength
+                * q[i]
+                * q[j]
+                * ((1.0 - erf(ar)) / (r * r) + screen * (-ar * ar).exp() / r)
+                / r;
+            for x in 0..3 {
+                force[i][x] += w * d[x];
+                force[j][x] -= w * d[x];
+            }
+        }
+    }
+
+    let mut phase = vec![0.0; n];
+    for (k, c) in &e.wave {
+        let (mut cos, mut sin) = (0.0, 0.0);
+        for i in 0..n {
+            phase[i] = k[0] * columns.px[i] + k[1] * columns.py[i] + k[2] * columns.pz[i];
+            cos += q[i] * phase[i].cos();
+            sin += q[i] * phase[i].sin();
+        }
+        for i in 0..n {
+            if q[i] == 0.0 {
+                continue;
+            }
+            let w = strength * q[i] * c * (cos * phase[i].sin() - sin * phase[i].cos());
+            for x in 0..3 {
+                force[i][x] += w * k[x];
+            }
+        }
+    }
+
+    return force;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::anomaly::{add_particle_by, e, vacuum};
+    use crate::arena::establish;
+    use crate::columns::gather;
+    use crate::rigid::make_rigid;
+
+    static SIZE: [f64; 3] = [100.0, 100.0, 100.0];
+
+    #[test]
+    fn tied_pairs_and_rigid_bodies_wrap_whole() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e([49.0, 0.0, 0.0], [0.0; 3], true));
+        add_particle_by(&mut root, top, e([52.0, 0.0, 0.0], [0.0; 3], true));
+        let body = add_particle_by(&mut root, top, vacuum()).unwrap();
+        add_particle_by(&mut root, body, e([-51.0, 10.0, 0.0], [0.0; 3], true));
+        add_particle_by(&mut root, body, e([-48.0, 10.0, 0.0], [0.0; 3], true));
+        make_rigid(&mut root.anomaly[2], Some(SIZE));
+
+        let mut columns = gather(&root);
+        let bond = vec![Constraint::Bond {
+            a: columns.id[0],
+            b: columns.id[1],
+            length: 3.0,
+            stiffness: 1.0,
+        }];
+        wrap(&mut root, &bond, &mut columns, SIZE);
+
+        // the pair centered past the side comes back whole on the other one
+        assert_eq!(columns.position(0), [-51.0, 0.0, 0.0]);
+        assert_eq!(columns.position(1), [-48.0, 0.0, 0.0]);
+        // the body centered inside stays, its center with it
+        assert_eq!(columns.position(2), [-51.0, 10.0, 0.0]);
+        assert_eq!(root.anomaly[2].rigid[0].center, [-49.5, 10.0, 0.0]);
+
+        for k in 2..4 {
+            columns.px[k] -= 2.0;
+        }
+        wrap(&mut root, &vec![], &mut columns, SIZE);
+        assert_eq!(columns.position(2), [47.0, 10.0, 0.0]);
+        assert_eq!(columns.position(3), [50.0, 10.0, 0.0]);
+        assert_eq!(root.anomaly[2].rigid[0].center, [50.5, 10.0, 0.0]);
+    }
+
+    // a small neutral cluster in a large box feels next to nothing of its images
+    #[test]
+    fn a_large_box_sums_like_the_direct_sum() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        let place = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.2, -0.3],
+            [-0.4, 0.9, 0.5],
+            [0.3, -0.7, 1.1],
+        ];
+        for p in place {
+            add_particle_by(&mut root, top, e(p, [0.0; 3], true));
+        }
+        root.periodic = vec![Arc::new(ewald_base(&periodic_base([400.0; 3], 1e-8)))];
+        let mut columns = gather(&root);
+        columns.charge = vec![1.0, -1.0, 1.0, -1.0];
+
+        let force = ewald_forces(&columns, &columns.periodic[0], 1.0);
+        for i in 0..4 {
+            let mut direct = [0.0, 0.0, 0.0];
+            for j in (0..4).filter(|j| *j != i) {
+                let d = sbtr_f64_3(columns.position(i), columns.position(j));
+                let r = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                let w = columns.charge[i] * columns.charge[j] / (r * r * r);
+                direct = dd_f64_3(direct, mltply_f64_3(d, w));
+            }
+            for x in 0..3 {
+                assert!(
+                    (force[i][x] - direct[x]).abs() < 1e-4,
+                    "{:?} {:?}",
+                    force[i],
+                    direct
+                );
+            }
+        }
+    }
+}
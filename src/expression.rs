@@ -0,0 +1,366 @@
+use crate::anomaly::{
+    component_property, has_component_property, Component, AF_F64, CR, EC, EN, EP_F64, HB_F64, IN0,
+    IN1, IN2, KN, LS_F64, ML_F64, MS, SO0, SO1, SO2, SP, TS_F64, WA, WD,
+};
+
+// force laws written as text and compiled once into a little stack program;
+// the value is the force along the line from a to b in MeV per planck length,
+// positive pushes the two apart
+//
+//   coupling * EP * a.EC * b.EC * exp(-r / range) / r^2
+//
+// r         distance in planck lengths
+// range     how far the force reaches, planck lengths
+// coupling  the domain value of the force
+// a.EC b.MS properties of the two components, 0 when missing
+// EP LS ML HB AF TS pi
+// exp ln sqrt abs sin cos min max, + - * / ^ and parentheses
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Sin,
+    Cos,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Number(f64),
+    Distance,
+    Range,
+    Coupling,
+    PropertyA(f64),
+    PropertyB(f64),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Neg,
+    Call(Function),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+pub struct Expression {
+    pub source: String,
+    code: Vec<Op>,
+}
+
+pub struct Input<'a> {
+    pub distance: f64,
+    pub range: f64,
+    pub coupling: f64,
+    pub a: &'a Component,
+    pub b: &'a Component,
+}
+
+pub fn compile(text: &str) -> Result<Expression, String> {
+    let token = tokenize(text)?;
+    let mut parser = Parser {
+        token,
+        at: 0,
+        code: vec![],
+    };
+    parser.sum()?;
+    if parser.at < parser.token.len() {
+        return Err(format!(
+            "law {}: unexpected {:?}",
+            text, parser.token[parser.at]
+        ));
+    }
+
+    return Ok(Expression {
+        source: text.to_string(),
+        code: parser.code,
+    });
+}
+
+pub fn evaluate(e: &Expression, input: &Input) -> f64 {
+    let mut stack: Vec<f64> = Vec::with_capacity(e.code.len());
+    for op in &e.code {
+        let v = match op {
+            Op::Number(v) => *v,
+            Op::Distance => input.distance,
+            Op::Range => input.range,
+            Op::Coupling => input.coupling,
+            Op::PropertyA(name) => property_or_zero(input.a, *name),
+            Op::PropertyB(name) => property_or_zero(input.b, *name),
+            Op::Neg => -stack.pop().unwrap(),
+            Op::Call(f) if unary(*f) => {
+                let x = stack.pop().unwrap();
+                match f {
+                    Function::Exp => x.exp(),
+                    Function::Ln => x.ln(),
+                    Function::Sqrt => x.sqrt(),
+                    Function::Abs => x.abs(),
+                    Function::Sin => x.sin(),
+                    _ => x.cos(),
+                }
+            }
+            _ => {
+                let y = stack.pop().unwrap();
+                let x = stack.pop().unwrap();
+                match op {
+                    Op::Add => x + y,
+                    Op::Sub => x - y,
+                    Op::Mul => x * y,
+                    Op::Div => x / y,
+                    Op::Pow => x.powf(y),
+                    Op::Call(Function::Min) => x.min(y),
+                    _ => x.max(y),
+                }
+            }
+        };
+        stack.push(v);
+    }
+
+    return stack.pop().unwrap_or(0.0);
+}
+
+fn property_or_zero(c: &Component, name: f64) -> f64 {
+    if has_component_property(c, name) {
+        return component_property(c, name);
+    }
+    return 0.0;
+}
+
+fn unary(f: Function) -> bool {
+    return f != Function::Min && f != Function::Max;
+}
+
+pub fn property_code(name: &str) -> Option<f64> {
+    return match name {
+        "EC" => Some(EC),
+        "SP" => Some(SP),
+        "MS" => Some(MS),
+        "CR" => Some(CR),
+        "IN0" => Some(IN0),
+        "IN1" => Some(IN1),
+        "IN2" => Some(IN2),
+        "SO0" => Some(SO0),
+        "SO1" => Some(SO1),
+        "SO2" => Some(SO2),
+        "WD" => Some(WD),
+        "WA" => Some(WA),
+        "EN" => Some(EN),
+        "KN" => Some(KN),
+        _ => None,
+    };
+}
+
+fn constant(name: &str) -> Option<f64> {
+    return match name {
+        "EP" => Some(EP_F64),
+        "LS" => Some(LS_F64),
+        "ML" => Some(ML_F64),
+        "HB" => Some(HB_F64),
+        "AF" => Some(AF_F64),
+        "TS" => Some(TS_F64),
+        "pi" => Some(std::f64::consts::PI),
+        _ => None,
+    };
+}
+
+fn function(name: &str) -> Option<Function> {
+    return match name {
+        "exp" => Some(Function::Exp),
+        "ln" => Some(Function::Ln),
+        "sqrt" => Some(Function::Sqrt),
+        "abs" => Some(Function::Abs),
+        "sin" => Some(Function::Sin),
+        "cos" => Some(Function::Cos),
+        "min" => Some(Function::Min),
+        "max" => Some(Function::Max),
+        _ => None,
+    };
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut ret = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let word: String = chars[start..i].iter().collect();
+            let v = word
+                .parse::<f64>()
+                .map_err(|_| format!("law {}: {} is not a number", text, word))?;
+            ret.push(Token::Number(v));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            ret.push(Token::Name(chars[start..i].iter().collect()));
+        } else if "+-*/^(),.".contains(c) {
+            ret.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("law {}: unexpected {}", text, c));
+        }
+    }
+
+    return Ok(ret);
+}
+
+// recursive descent, emitting code in postfix order as it goes
+struct Parser {
+    token: Vec<Token>,
+    at: usize,
+    code: Vec<Op>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.token.get(self.at);
+    }
+
+    fn symbol(&mut self, s: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(s)) {
+            self.at += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn expect(&mut self, s: char) -> Result<(), String> {
+        if self.symbol(s) {
+            return Ok(());
+        }
+        return Err(format!("law: expected {} at {:?}", s, self.peek()));
+    }
+
+    fn sum(&mut self) -> Result<(), String> {
+        self.product()?;
+        loop {
+            if self.symbol('+') {
+                self.product()?;
+                self.code.push(Op::Add);
+            } else if self.symbol('-') {
+                self.product()?;
+                self.code.push(Op::Sub);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<(), String> {
+        self.sign()?;
+        loop {
+            if self.symbol('*') {
+                self.sign()?;
+                self.code.push(Op::Mul);
+            } else if self.symbol('/') {
+                self.sign()?;
+                self.code.push(Op::Div);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn sign(&mut self) -> Result<(), String> {
+        if self.symbol('-') {
+            self.sign()?;
+            self.code.push(Op::Neg);
+            return Ok(());
+        }
+        return self.power();
+    }
+
+    // ^ binds tighter than a sign on its left and groups to the right
+    fn power(&mut self) -> Result<(), String> {
+        self.atom()?;
+        if self.symbol('^') {
+            self.sign()?;
+            self.code.push(Op::Pow);
+        }
+        return Ok(());
+    }
+
+    fn atom(&mut self) -> Result<(), String> {
+        let t = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err("law: ends too early".to_string()),
+        };
+        self.at += 1;
+
+        match t {
+            Token::Number(v) => self.code.push(Op::Number(v)),
+            Token::Symbol('(') => {
+                self.sum()?;
+                self.expect(')')?;
+            }
+            Token::Name(n) if (n == "a" || n == "b") && self.symbol('.') => {
+                let name = match self.peek() {
+                    Some(Token::Name(p)) => p.clone(),
+                    t => return Err(format!("law: expected a property at {:?}", t)),
+                };
+                self.at += 1;
+                let code = property_code(&name)
+                    .ok_or_else(|| format!("law: unknown property {}", name))?;
+                self.code.push(if n == "a" {
+                    Op::PropertyA(code)
+                } else {
+                    Op::PropertyB(code)
+                });
+            }
+            Token::Name(n) if n == "r" => self.code.push(Op::Distance),
+            Token::Name(n) if n == "range" => self.code.push(Op::Range),
+            Token::Name(n) if n == "coupling" => self.code.push(Op::Coupling),
+            Token::Name(n) => {
+                if let Some(v) = constant(&n) {
+                    self.code.push(Op::Number(v));
+                } else if let Some(f) = function(&n) {
+                    self.expect('(')?;
+                    self.sum()?;
+                    if !unary(f) {
+                        self.expect(',')?;
+                        self.sum()?;
+                    }
+                    self.expect(')')?;
+                    self.code.push(Op::Call(f));
+                } else {
+                    return Err(format!("law: unknown name {}", n));
+                }
+            }
+            Token::Symbol(s) => return Err(format!("law: unexpected {}", s)),
+        }
+
+        return Ok(());
+    }
+}
@@ -40,7 +40,7 @@ pub fn vector_length(x: [f32; 3]) -> f32 {
     return (x[0].powi(2) + x[1].powi(2) + x[2].powi(2)).sqrt();
 }
 
-pub fn gen_f32_3(base: f32, range: f32, rng: &mut ThreadRng) -> [f32; 3] {
+pub fn gen_f32_3(base: f32, range: f32, rng: &mut impl Rng) -> [f32; 3] {
     return [
         rng.gen_range(base - range..base + range),
         rng.gen_range(base - range..base + range),
@@ -1,7 +1,6 @@
-use rand::rngs::ThreadRng;
 use rand::Rng;
 
-pub fn gen_f64_3(base: f64, range: f64, rng: &mut ThreadRng) -> [f64; 3] {
+pub fn gen_f64_3(base: f64, range: f64, rng: &mut impl Rng) -> [f64; 3] {
     return [
         rng.gen_range(base - range..base + range),
         rng.gen_range(base - range..base + range),
@@ -25,3 +24,35 @@ pub fn mltply_f64_3(a: [f64; 3], b: f64) -> [f64; 3] {
 pub fn vector_length(x: [f64; 3]) -> f64 {
     return (x[0].powi(2) + x[1].powi(2) + x[2].powi(2)).sqrt();
 }
+
+pub fn sbtr_f64_3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    return [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+}
+
+pub fn dd_f64_3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    return [a[0] + b[0], a[1] + b[1], a[2] + b[2]];
+}
+
+pub fn dot_product(a: [f64; 3], b: [f64; 3]) -> f64 {
+    return a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+}
+
+pub fn cross_product(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    return [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ];
+}
+
+// rodrigues rotation of v around a normalized axis
+pub fn rotate_f64_3(v: [f64; 3], axis: [f64; 3], angle: f64) -> [f64; 3] {
+    let sin_t = angle.sin();
+    let cos_t = angle.cos();
+    let part_one = dd_f64_3(
+        mltply_f64_3(v, cos_t),
+        mltply_f64_3(cross_product(axis, v), sin_t),
+    );
+    let part_two = mltply_f64_3(axis, dot_product(axis, v) * (1.0 - cos_t));
+    return dd_f64_3(part_one, part_two);
+}
@@ -0,0 +1,166 @@
+use std::f64::consts::TAU;
+
+use crate::anomaly::{
+    accelerate, component_inertia, component_position, component_property, has_component_property,
+    Anomaly, Component, AF_F64, EC, EP_F64, IN0, LS_F64,
+};
+use crate::f64_3::{
+    cross_product, dd_f64_3, dot_product, mltply_f64_3, nrmlz_f64_3, sbtr_f64_3, vector_length,
+};
+use crate::spin::spin_precess;
+
+// electric and magnetic fields are in MeV per planck length per unit charge,
+// the same units the spin dipoles produce
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Uniform {
+        e: [f64; 3],
+        b: [f64; 3],
+    },
+    // uniform magnetic field inside a cylinder around the axis, nothing outside
+    Solenoid {
+        center: [f64; 3],
+        axis: [f64; 3],
+        radius: f64,
+        b: f64,
+    },
+    // a fixed charge that is not a particle of the anomaly
+    Charge {
+        center: [f64; 3],
+        charge: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub shape: Shape,
+    pub frequency: f64,
+    pub phase: f64,
+}
+
+pub fn uniform(e: [f64; 3], b: [f64; 3]) -> Field {
+    return Field {
+        shape: Shape::Uniform { e, b },
+        frequency: 0.0,
+        phase: 0.0,
+    };
+}
+
+pub fn solenoid(center: [f64; 3], axis: [f64; 3], radius: f64, b: f64) -> Field {
+    return Field {
+        shape: Shape::Solenoid {
+            center,
+            axis: nrmlz_f64_3(axis),
+            radius,
+            b,
+        },
+        frequency: 0.0,
+        phase: 0.0,
+    };
+}
+
+pub fn point_charge(center: [f64; 3], charge: f64) -> Field {
+    return Field {
+        shape: Shape::Charge { center, charge },
+        frequency: 0.0,
+        phase: 0.0,
+    };
+}
+
+// electric and magnetic field at a position, at the given anomaly clock
+pub fn field_at(f: &Field, position: [f64; 3], clock: f64) -> ([f64; 3], [f64; 3]) {
+    let strength = (TAU * f.frequency * clock + f.phase).cos();
+
+    let (e, b) = match f.shape {
+        Shape::Uniform { e, b } => (e, b),
+        Shape::Solenoid {
+            center,
+            axis,
+            radius,
+            b,
+        } => {
+            let offset = sbtr_f64_3(position, center);
+            let radial = sbtr_f64_3(offset, mltply_f64_3(axis, dot_product(offset, axis)));
+            if vector_length(radial) < radius {
+                ([0.0, 0.0, 0.0], mltply_f64_3(axis, b))
+            } else {
+                ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0])
+            }
+        }
+        Shape::Charge { center, charge } => {
+            let r = sbtr_f64_3(position, center);
+            let d = vector_length(r);
+            if d == 0.0 {
+                ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0])
+            } else {
+                (
+                    mltply_f64_3(r, AF_F64 * EP_F64 * charge / d.powi(3)),
+                    [0.0, 0.0, 0.0],
+                )
+            }
+        }
+    };
+
+    return (mltply_f64_3(e, strength), mltply_f64_3(b, strength));
+}
+
+pub fn fields_at(fields: &Vec<Field>, position: [f64; 3], clock: f64) -> ([f64; 3], [f64; 3]) {
+    let mut e = [0.0, 0.0, 0.0];
+    let mut b = [0.0, 0.0, 0.0];
+    for f in fields {
+        let (fe, fb) = field_at(f, position, clock);
+        e = dd_f64_3(e, fe);
+        b = dd_f64_3(b, fb);
+    }
+
+    return (e, b);
+}
+
+// the fields of an anomaly act on every component below it
+pub fn field_act(anom: &mut Anomaly, time: f64) {
+    if anom.field.is_empty() {
+        return;
+    }
+
+    let fields = &anom.field;
+    let clock = anom.clock;
+    for c in anom.component.iter_mut() {
+        field_component(fields, clock, time, c);
+    }
+    for a in anom.anomaly.iter_mut() {
+        field_anomaly(fields, clock, time, a);
+    }
+}
+
+fn field_anomaly(fields: &Vec<Field>, clock: f64, time: f64, anom: &mut Anomaly) {
+    for c in anom.component.iter_mut() {
+        field_component(fields, clock, time, c);
+    }
+    for a in anom.anomaly.iter_mut() {
+        field_anomaly(fields, clock, time, a);
+    }
+}
+
+fn field_component(fields: &Vec<Field>, clock: f64, time: f64, c: &mut Component) {
+    for k in c.component.iter_mut() {
+        field_component(fields, clock, time, k);
+    }
+
+    if !has_component_property(c, EC) || !has_component_property(c, IN0) {
+        return;
+    }
+    let charge = component_property(c, EC);
+    if charge == 0.0 {
+        return;
+    }
+
+    let (e, b) = fields_at(fields, component_position(c), clock);
+
+    // lorentz force q (E + v/c x B)
+    let beta = mltply_f64_3(component_inertia(c), 1.0 / LS_F64);
+    let lorentz = mltply_f64_3(dd_f64_3(e, cross_product(beta, b)), charge);
+
+    accelerate(lorentz, time, c);
+    spin_precess(b, time, c);
+}
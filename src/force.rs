@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use crate::anomaly::{
+    accelerate, component_position, component_property, has_component_property, Component, Force,
+    Property, EC, EP_F64, ML_F64, MS,
+};
+use crate::arena::Id;
+use crate::expression::{compile, evaluate, property_code, Expression, Input};
+use crate::f64_3::{mltply_f64_3, sbtr_f64_3, vector_length};
+use crate::wavepacket::{smeared, wavepacket_width};
+
+// what a Force means for a pair of components at some distance:
+//
+// domain  - each entry lists properties; the pair is in the domain when both
+//           components carry every property of one entry, an empty domain lets
+//           every pair through. nested forces are only reached through the gate
+// value   - the values of the matched entry are couplings; a leaf turns each of
+//           its properties into a law with that coupling, a parent carries the
+//           product of its values down to its children as a common factor
+// range   - boundaries in meters, ascending; past the last one the force and
+//           everything nested in it is out of reach, no range reaches everywhere
+// regimes - with several ranges, regime k lies between range[k - 1] and range[k];
+//           a parent with one child per regime hands the pair to child k, a leaf
+//           with one domain entry per regime gates and couples with entry k,
+//           otherwise every child and entry holds across all regimes
+// law     - a leaf carrying a compiled expression becomes one law evaluating it,
+//           coupled by the product of its matched entry (see expression.rs)
+//
+// top level inverse square laws are stepped in columns instead when the root
+// carries the same law, see central_laws; everywhere else they are applied here
+
+#[derive(Clone, Copy)]
+pub struct Law<'a> {
+    pub name: f64,
+    pub coupling: f64,
+    // planck lengths
+    pub reach: f64,
+    pub expression: Option<&'a Expression>,
+}
+
+pub fn carries(c: &Component, entry: &Component) -> bool {
+    return entry
+        .property
+        .iter()
+        .all(|p| has_component_property(c, p.name));
+}
+
+// which regime a distance in planck lengths falls into, None when out of reach
+pub fn regime(range: &Vec<f64>, distance: f64) -> Option<usize> {
+    if range.is_empty() {
+        return Some(0);
+    }
+    return range.iter().position(|r| distance <= r * ML_F64);
+}
+
+pub fn force_laws<'a>(f: &'a Force, a: &Component, b: &Component, distance: f64) -> Vec<Law<'a>> {
+    let mut ret = vec![];
+    collect_laws(f, a, b, distance, 1.0, f64::MAX, &mut ret);
+    return ret;
+}
+
+fn collect_laws<'a>(
+    f: &'a Force,
+    a: &Component,
+    b: &Component,
+    distance: f64,
+    scale: f64,
+    reach: f64,
+    ret: &mut Vec<Law<'a>>,
+) {
+    let k = match regime(&f.range, distance) {
+        Some(k) => k,
+        None => return,
+    };
+    let reach = match f.range.last() {
+        Some(r) => reach.min(r * ML_F64),
+        None => reach,
+    };
+    let per_regime = f.range.len() > 1;
+
+    let entry = if f.domain.is_empty() {
+        None
+    } else if per_regime && f.force.is_empty() && f.domain.len() == f.range.len() {
+        Some(&f.domain[k]).filter(|d| carries(a, d) && carries(b, d))
+    } else {
+        f.domain.iter().find(|d| carries(a, d) && carries(b, d))
+    };
+    if !f.domain.is_empty() && entry.is_none() {
+        return;
+    }
+
+    let product = entry.map_or(1.0, |e| e.property.iter().map(|p| p.value).product());
+
+    if f.force.is_empty() {
+        if let Some(l) = f.law.first() {
+            ret.push(Law {
+                name: entry.map_or(0.0, |e| e.property.first().map_or(0.0, |p| p.name)),
+                coupling: scale * product,
+                reach,
+                expression: Some(l),
+            });
+        } else if let Some(e) = entry {
+            for p in &e.property {
+                ret.push(Law {
+                    name: p.name,
+                    coupling: scale * p.value,
+                    reach,
+                    expression: None,
+                });
+            }
+        }
+        return;
+    }
+
+    if per_regime && f.force.len() == f.range.len() {
+        collect_laws(&f.force[k], a, b, distance, scale * product, reach, ret);
+    } else {
+        for n in &f.force {
+            collect_laws(n, a, b, distance, scale * product, reach, ret);
+        }
+    }
+}
+
+// a single inverse square law of charge or mass, the kind columns step
+pub fn is_central(f: &Force) -> bool {
+    return f.force.is_empty()
+        && f.law.is_empty()
+        && f.range.len() <= 1
+        && f.domain.len() == 1
+        && f.domain[0].property.len() == 1
+        && (f.domain[0].property[0].name == EC || f.domain[0].property[0].name == MS);
+}
+
+// like charges repel, masses attract
+pub fn central_sign(name: f64) -> f64 {
+    return if name == MS { -1.0 } else { 1.0 };
+}
+
+// the pairwise counterpart of central_kernel for laws met below the top level
+pub fn central_apply(law: &Law, a: &mut Component, b: &mut Component, time: f64) {
+    if !has_component_property(a, law.name) || !has_component_property(b, law.name) {
+        return;
+    }
+    let r = sbtr_f64_3(component_position(b), component_position(a));
+    let d = vector_length(r);
+    if d == 0.0 {
+        return;
+    }
+
+    let strength = central_sign(law.name)
+        * law.coupling
+        * EP_F64
+        * component_property(a, law.name)
+        * component_property(b, law.name);
+    let spread =
+        wavepacket_width(a).unwrap_or(0.0).powi(2) + wavepacket_width(b).unwrap_or(0.0).powi(2);
+    let f = mltply_f64_3(r, strength * smeared(d, spread.sqrt()) / (d * d * d));
+    accelerate(f, time, b);
+    accelerate(mltply_f64_3(f, -1.0), time, a);
+}
+
+pub fn expression_apply(
+    law: &Law,
+    e: &Expression,
+    a: &mut Component,
+    b: &mut Component,
+    time: f64,
+) {
+    let r = sbtr_f64_3(component_position(b), component_position(a));
+    let d = vector_length(r);
+    if d == 0.0 {
+        return;
+    }
+
+    let value = evaluate(
+        e,
+        &Input {
+            distance: d,
+            range: law.reach,
+            coupling: law.coupling,
+            a,
+            b,
+        },
+    );
+    if !value.is_finite() {
+        return;
+    }
+    let f = mltply_f64_3(r, value / d);
+    accelerate(f, time, b);
+    accelerate(mltply_f64_3(f, -1.0), time, a);
+}
+
+// a copy of the force tree for another owner, compiled laws are shared
+pub fn share_force(f: &Force) -> Force {
+    return Force {
+        force: f.force.iter().map(share_force).collect(),
+        range: f.range.clone(),
+        domain: f
+            .domain
+            .iter()
+            .map(|d| Component {
+                component: vec![],
+                composition: vec![],
+                property: d
+                    .property
+                    .iter()
+                    .map(|p| Property {
+                        name: p.name,
+                        value: p.value,
+                    })
+                    .collect(),
+                id: Id::default(),
+            })
+            .collect(),
+        law: f.law.clone(),
+    };
+}
+
+// a leaf force with a law, as written in scenario files:
+//
+//   EC=0.0073 MS=1 range 1e-15 : coupling * a.EC * b.EC * exp(-r / range) / r^2
+//
+// the names before the colon make one domain entry, the ranges are in meters
+pub fn parse_force(text: &str) -> Result<Force, String> {
+    let (head, law) = match text.split_once(':') {
+        Some(s) => s,
+        None => return Err(format!("force {}: no law after a colon", text)),
+    };
+
+    let mut property = vec![];
+    let mut range = vec![];
+    let mut ranges = false;
+    for w in head.split_whitespace() {
+        if w == "range" {
+            ranges = true;
+        } else if ranges {
+            range.push(
+                w.parse::<f64>()
+                    .map_err(|_| format!("force {}: {} is not a number", text, w))?,
+            );
+        } else {
+            let (name, value) = w.split_once('=').unwrap_or((w, "1"));
+            property.push(Property {
+                name: property_code(name)
+                    .ok_or_else(|| format!("force {}: unknown property {}", text, name))?,
+                value: value
+                    .parse::<f64>()
+                    .map_err(|_| format!("force {}: {} is not a number", text, value))?,
+            });
+        }
+    }
+
+    let domain = if property.is_empty() {
+        vec![]
+    } else {
+        vec![Component {
+            component: vec![],
+            composition: vec![],
+            property,
+            id: Id::default(),
+        }]
+    };
+
+    return Ok(Force {
+        force: vec![],
+        range,
+        domain,
+        law: vec![Arc::new(compile(law.trim())?)],
+    });
+}
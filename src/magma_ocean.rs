@@ -52,6 +52,23 @@ pub fn magma(flow: u32, scale: f32) -> Magma {
     return lava_flow;
 }
 
+// two point flow laid along a direction instead of a random diagonal
+pub fn magma_oriented(direction: [f32; 3], scale: f32) -> Magma {
+    let axis = nrmlz_f32_3(direction);
+
+    return Magma {
+        positions: vec![
+            Position {
+                position: mltply_f32_3(axis, -2.5 * scale),
+            },
+            Position {
+                position: mltply_f32_3(axis, 2.5 * scale),
+            },
+        ],
+        indices: vec![0, 1],
+    };
+}
+
 pub fn petrify(flow: Magma) -> Stone {
     if flow.positions.len() > 2 {
         return petrify_flow(flow);
@@ -1,6 +1,4 @@
 #![allow(warnings)]
-use rand::Rng;
-
 use cgmath::{Matrix3, Matrix4, Point3, Rad, Vector3};
 use glam::{
     f32::{Mat3, Vec3},
@@ -65,10 +63,7 @@ mod display_mods;
 use display_mods::{oclock, record_nanos, Groupable};
 
 mod f32_3;
-use f32_3::gen_f32_3;
-
 mod f64_3;
-use f64_3::{gen_f64_3, mltply_f64_3, nrmlz_f64_3};
 
 mod positions;
 use positions::{Normal, Position};
@@ -80,7 +75,43 @@ mod magma_ocean;
 use magma_ocean::Stone;
 
 mod anomaly;
-use anomaly::{add_particle_by, e, progress, q, view, Anomaly, LS_F64, TS_F64};
+use anomaly::{progress, progress_by, Anomaly, TS_F64};
+
+mod arena;
+mod clock;
+mod cluster;
+use cluster::watch_step;
+mod columns;
+mod compute;
+mod conservation;
+mod constraint;
+use compute::{compute_base, compute_check, compute_headless, compute_step, COMPUTE_TOLERANCE};
+mod decay;
+mod distribution;
+mod ensemble;
+use ensemble::run_ensemble;
+mod ewald;
+mod expression;
+mod field;
+mod force;
+mod monte_carlo;
+use monte_carlo::sample_step;
+mod nuclear;
+
+mod query;
+mod radiation;
+mod reaction;
+mod rigid;
+
+mod scenario;
+use scenario::{read_scenario, scenario_anomaly, scenario_base, scenario_sampler, Scenario};
+
+mod simulation;
+use simulation::{command, latest, simulate, stop, Command, Simulation, Snapshot};
+
+mod spin;
+mod thermostat;
+mod wavepacket;
 
 mod moving_around;
 use moving_around::{
@@ -97,12 +128,92 @@ fn main() -> Result<(), impl Error> {
     // The start of this example is exactly the same as `triangle`. You should read the `triangle`
     // example if you haven't done so yet.
 
+    // compares the compute shader with the cpu columns headless, lavapipe is enough
+    if flag("--compute-check") {
+        let compute = compute_headless().expect("no vulkan device to compute on");
+        let anomaly = anomaly_of(&scenario_of_args());
+        let deviation = compute_check(&compute, &anomaly, 10.0 * TS_F64);
+        println!("compute deviation: {:e}", deviation);
+        if deviation > COMPUTE_TOLERANCE {
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    // every seed and swept setting of the scenario headless, statistics to csv
+    if flag("--ensemble") {
+        let text = match scenario_path() {
+            Some(path) => match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("ensemble: {}: {}", path, e);
+                    std::process::exit(1);
+                }
+            },
+            None => String::new(),
+        };
+        match run_ensemble(&text) {
+            Ok(file) => println!("ensemble written to {}", file),
+            Err(e) => {
+                eprintln!("ensemble: {}", e);
+                std::process::exit(1);
+            }
+        }
+        std::process::exit(0);
+    }
+
     let event_loop = EventLoop::new().unwrap();
     let mut app = App::new(&event_loop);
 
     event_loop.run_app(&mut app)
 }
 
+// moves the origin to the camera once it is far from it and tells the simulation
+fn reanchor(u: &mut U61qate, sim: &Simulation) {
+    let p = u.view_point.position;
+    if (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt() < ANCHOR_DISTANCE {
+        return;
+    }
+    for k in 0..3 {
+        u.origin[k] += (p[k] / VIEW_SCALE) as f64;
+        u.center.position[k] -= p[k];
+        u.view_point.position[k] = 0.0;
+    }
+    command(sim, Command::Origin(u.origin));
+}
+
+// the first argument that is not a --flag names the scenario file
+fn scenario_path() -> Option<String> {
+    return std::env::args().skip(1).find(|a| !a.starts_with("--"));
+}
+
+fn scenario_of_args() -> Scenario {
+    return match scenario_path() {
+        Some(path) => match read_scenario(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("scenario: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => scenario_base(),
+    };
+}
+
+fn anomaly_of(scenario: &Scenario) -> Anomaly {
+    return match scenario_anomaly(scenario) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("scenario: {}", e);
+            std::process::exit(1);
+        }
+    };
+}
+
+fn flag(name: &str) -> bool {
+    return std::env::args().any(|a| a == name);
+}
+
 struct App {
     instance: Arc<Instance>,
     device: Arc<Device>,
@@ -112,6 +223,11 @@ struct App {
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
     uniform_buffer_allocator: SubbufferAllocator,
+    simulation: Simulation,
+    shown: Option<Arc<Snapshot>>,
+    drawn: Vec<Bv>,
+    frames: u64,
+    frames_start: Instant,
     rcx: Option<RenderContext>,
     u61qate: U61qate,
 }
@@ -129,9 +245,16 @@ struct RenderContext {
     rotation_start: Instant,
 }
 
+// the camera moves in f32 around a world origin in f64, which follows it once
+// it strays further than ANCHOR_DISTANCE; the simulation places stones relative
+// to the origin, so f32 only ever holds what is near the camera
+static VIEW_SCALE: f32 = 0.01;
+static ANCHOR_DISTANCE: f32 = 10.0;
+
 struct U61qate {
+    // planck lengths
+    origin: [f64; 3],
     view_point: Position,
-    u61q: Anomaly,
     center: Position,
     up_direction: Position,
     rot_static: bool,
@@ -233,37 +356,34 @@ impl App {
             },
         );
 
-        let mut rng = rand::thread_rng();
-
-        let mut anomaly = Anomaly {
-            anomaly: vec![],
-            component: vec![],
-            force: vec![],
+        let mut scenario = scenario_of_args();
+        let anomaly = anomaly_of(&scenario);
+        let mut watch = scenario.cluster.pop();
+        let sampler = scenario_sampler(&scenario);
+
+        let compute = if flag("--compute") {
+            compute_base(
+                &queue,
+                &memory_allocator,
+                &descriptor_set_allocator,
+                &command_buffer_allocator,
+            )
+        } else {
+            None
         };
-
-        let k = 10;
-
-        for _ in 0..k {
-            add_particle_by(
-                &mut anomaly,
-                e(
-                    gen_f32_3(0.0, 69.0, &mut rng),
-                    mltply_f64_3(nrmlz_f64_3(gen_f64_3(0.0, 10.0, &mut rng)), LS_F64),
-                    true,
-                ),
-            );
-            add_particle_by(
-                &mut anomaly,
-                q(
-                    gen_f32_3(0.0, 69.0, &mut rng),
-                    mltply_f64_3(nrmlz_f64_3(gen_f64_3(0.0, 10.0, &mut rng)), LS_F64),
-                    true,
-                    true,
-                    rng.gen_range(0..3),
-                    rng.gen_range(0..1),
-                ),
-            );
-        }
+        let mut advance: Box<dyn FnMut(&mut Anomaly) + Send> = match (sampler, compute) {
+            (Some(mut sampler), _) => Box::new(move |a: &mut Anomaly| sample_step(&mut sampler, a)),
+            (None, Some(compute)) => Box::new(move |a: &mut Anomaly| {
+                progress_by(a, TS_F64, &mut |c, l, t| compute_step(&compute, c, l, t))
+            }),
+            (None, None) => Box::new(|a: &mut Anomaly| progress(a, TS_F64)),
+        };
+        let simulation = simulate(anomaly, move |a: &mut Anomaly| {
+            advance(a);
+            if let Some(w) = &mut watch {
+                watch_step(w, a);
+            }
+        });
 
         // Create a query pool for occlusion queries, with 3 slots.
         let query_pool = QueryPool::new(
@@ -284,9 +404,14 @@ impl App {
             descriptor_set_allocator,
             command_buffer_allocator,
             uniform_buffer_allocator,
+            simulation,
+            shown: None,
+            drawn: vec![],
+            frames: 0,
+            frames_start: Instant::now(),
             rcx: None,
             u61qate: U61qate {
-                u61q: anomaly,
+                origin: [0.0, 0.0, 0.0],
                 view_point: Position {
                     position: [0.0, -1.0, 1.0],
                 },
@@ -425,6 +550,7 @@ impl ApplicationHandler for App {
 
         match event {
             WindowEvent::CloseRequested => {
+                stop(&mut self.simulation);
                 event_loop.exit();
             }
             WindowEvent::SurfaceResized(_) => {
@@ -528,19 +654,41 @@ impl ApplicationHandler for App {
                     );
                 }
 
-                progress(&mut self.u61qate.u61q, TS_F64);
-                let get = view(&mut self.u61qate.u61q);
+                reanchor(&mut self.u61qate, &self.simulation);
 
-                let mut bvs: Vec<Bv> = vec![];
+                // buffers are only rebuilt when the simulation published something new
+                if let Some(snapshot) = latest(&self.simulation) {
+                    for l in &snapshot.log {
+                        println!("{}", l);
+                    }
+                    self.drawn = vec![];
+                    for g in &snapshot.stone {
+                        let (vertex_buffer, normals_buffer, index_buffer) =
+                            load_buffers_short(g, self.memory_allocator.clone());
+                        self.drawn.push(Bv {
+                            v: vertex_buffer,
+                            n: normals_buffer,
+                            i: index_buffer,
+                        });
+                    }
+                    self.shown = Some(snapshot);
+                }
 
-                for mut g in get {
-                    let (vertex_buffer, normals_buffer, index_buffer) =
-                        load_buffers_short(&mut g, self.memory_allocator.clone());
-                    bvs.push(Bv {
-                        v: vertex_buffer,
-                        n: normals_buffer,
-                        i: index_buffer,
-                    });
+                self.frames += 1;
+                let elapsed = self.frames_start.elapsed().as_secs_f64();
+                if elapsed >= 1.0 {
+                    if let Some(shown) = &self.shown {
+                        rcx.window.set_title(&format!(
+                            "u61q  {:.0} steps/s  {:.0} fps  x{}  kT {:.3e} MeV{}",
+                            shown.rate,
+                            self.frames as f64 / elapsed,
+                            shown.speed,
+                            shown.temperature,
+                            if shown.paused { "  paused" } else { "" },
+                        ));
+                    }
+                    self.frames = 0;
+                    self.frames_start = Instant::now();
                 }
 
                 let window_size = rcx.window.surface_size();
@@ -607,17 +755,31 @@ impl ApplicationHandler for App {
                         ),
                     );
 
-                    let scale = Mat4::from_scale(Vec3::splat(0.01));
+                    let scale = Mat4::from_scale(Vec3::splat(VIEW_SCALE));
 
                     let mut rotation = 0.0;
                     if !self.u61qate.rot_static {
                         rotation = elapsed.as_secs() as f64
                             + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
                     }
+                    // stones come relative to the origin of their snapshot, turned
+                    // about the world origin and then moved to the camera's origin
+                    let shown = self.shown.as_ref().map_or([0.0; 3], |s| s.origin);
+                    let (sin, cos) = rotation.sin_cos();
+                    let turned = [
+                        shown[0] * cos + shown[2] * sin,
+                        shown[1],
+                        -shown[0] * sin + shown[2] * cos,
+                    ];
+                    let offset = Vector3::new(
+                        (turned[0] - self.u61qate.origin[0]) as f32,
+                        (turned[1] - self.u61qate.origin[1]) as f32,
+                        (turned[2] - self.u61qate.origin[2]) as f32,
+                    );
                     let rotation = Matrix3::from_angle_y(Rad(rotation as f32));
 
                     let uniform_data = vs::Data {
-                        world: Matrix4::from(rotation).into(),
+                        world: (Matrix4::from_translation(offset) * Matrix4::from(rotation)).into(),
                         view: (view * scale).to_cols_array_2d(),
                         proj: proj.to_cols_array_2d(),
                     };
@@ -689,7 +851,7 @@ impl ApplicationHandler for App {
                         )
                         .unwrap();
 
-                    for x in bvs {
+                    for x in &self.drawn {
                         builder
                             .begin_query(
                                 self.query_pool.clone(),
@@ -812,6 +974,18 @@ impl ApplicationHandler for App {
                         self.u61qate.rot_static = true;
                     }
                 }
+                PhysicalKey::Code(KeyCode::Space) => {
+                    command(&self.simulation, Command::Pause);
+                }
+                PhysicalKey::Code(KeyCode::KeyN) => {
+                    command(&self.simulation, Command::Step);
+                }
+                PhysicalKey::Code(KeyCode::Equal) => {
+                    command(&self.simulation, Command::Faster);
+                }
+                PhysicalKey::Code(KeyCode::Minus) => {
+                    command(&self.simulation, Command::Slower);
+                }
                 _ => (),
             },
             DeviceEvent::Key(RawKeyEvent {
@@ -962,7 +1136,7 @@ fn window_size_dependent_setup(
 }
 
 fn load_buffers_short(
-    stone: &mut Stone,
+    stone: &Stone,
     memory_allocator: Arc<StandardMemoryAllocator>,
     //) -> (u32, u32, u32) {
 ) -> (Subbuffer<[Position]>, Subbuffer<[Normal]>, Subbuffer<[u32]>) {
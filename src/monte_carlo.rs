@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::anomaly::{
+    component_property, has_component_property, Anomaly, Component, Force, EP_F64, SP,
+};
+use crate::arena::Id;
+use crate::columns::{central_laws, gather, scatter, shortest, source_of, Central, Columns};
+use crate::expression::{evaluate, Input};
+use crate::f64_3::{sbtr_f64_3, vector_length};
+use crate::force::{central_sign, force_laws, is_central};
+use crate::wavepacket::{erf, smeared, wavepacket_width};
+
+// equilibrium sampling instead of stepping: one component at a time is
+// displaced at random and the move is kept with probability exp(-dU / T)
+//
+// the root's central laws give the potential exactly; every other law met by
+// the pair the way progress meets it is integrated along the change in
+// distance, so energies are the central potential plus what the other laws
+// changed since sampling began. spin, fields, constraints and the nuclear
+// force are not sampled. in a periodic box pairs are taken at their nearest
+// image. temperatures are kT in MeV, steps in planck lengths
+
+pub static WORK_INTERVALS: usize = 16;
+
+pub struct Sampler {
+    pub temperature: f64,
+    pub step: f64,
+    pub every: u64,
+    pub proposed: u64,
+    pub accepted: u64,
+    pub sweeps: u64,
+    // MeV, one per sweep
+    pub energy: Vec<f64>,
+    pub gyration: Vec<f64>,
+    rng: StdRng,
+}
+
+pub fn sampler_base(temperature: f64, step: f64, every: u64, seed: u64) -> Sampler {
+    return Sampler {
+        temperature,
+        step,
+        every: every.max(1),
+        proposed: 0,
+        accepted: 0,
+        sweeps: 0,
+        energy: vec![],
+        gyration: vec![],
+        rng: StdRng::seed_from_u64(seed),
+    };
+}
+
+// a pair of columns and the force tree that holds between them
+struct Pair<'a> {
+    i: usize,
+    j: usize,
+    force: &'a Force,
+    a: &'a Component,
+    b: &'a Component,
+}
+
+// mirrors interact: components of one anomaly with each other, then the children pairwise
+fn collect_pairs<'a>(anom: &'a Anomaly, index: &HashMap<Id, usize>, ret: &mut Vec<Pair<'a>>) {
+    for f in anom.force.iter().filter(|f| !is_central(f)) {
+        for i in 0..anom.component.len() {
+            for j in i + 1..anom.component.len() {
+                component_pairs(f, &anom.component[i], &anom.component[j], index, ret);
+            }
+        }
+    }
+    for a in &anom.anomaly {
+        collect_pairs(a, index, ret);
+    }
+    for i in 0..anom.anomaly.len() {
+        for j in i + 1..anom.anomaly.len() {
+            anomaly_pairs(&anom.anomaly[i], &anom.anomaly[j], index, ret);
+        }
+    }
+}
+
+// mirrors anomaly_2_interact
+fn anomaly_pairs<'a>(
+    a: &'a Anomaly,
+    b: &'a Anomaly,
+    index: &HashMap<Id, usize>,
+    ret: &mut Vec<Pair<'a>>,
+) {
+    for i in &a.anomaly {
+        for j in &b.anomaly {
+            anomaly_pairs(i, j, index, ret);
+        }
+    }
+    for f in a.force.iter().filter(|f| !is_central(f)) {
+        for i in &a.component {
+            for j in &b.component {
+                component_pairs(f, i, j, index, ret);
+            }
+        }
+    }
+}
+
+fn component_pairs<'a>(
+    f: &'a Force,
+    a: &'a Component,
+    b: &'a Component,
+    index: &HashMap<Id, usize>,
+    ret: &mut Vec<Pair<'a>>,
+) {
+    for i in &a.component {
+        for j in &b.component {
+            component_pairs(f, i, j, index, ret);
+        }
+    }
+    if let (Some(i), Some(j)) = (index.get(&a.id), index.get(&b.id)) {
+        ret.push(Pair {
+            i: *i,
+            j: *j,
+            force: f,
+            a,
+            b,
+        });
+    }
+}
+
+// to the nearest image when the box is periodic
+fn distance(columns: &Columns, p: [f64; 3], q: [f64; 3]) -> f64 {
+    return vector_length(shortest(columns, sbtr_f64_3(p, q)));
+}
+
+// the central potential of columns i and j at distance d, MeV
+fn central_potential(columns: &Columns, laws: &Vec<Central>, i: usize, j: usize, d: f64) -> f64 {
+    if d == 0.0 {
+        return 0.0;
+    }
+    let spread = (columns.width[i].powi(2) + columns.width[j].powi(2)).sqrt();
+    let cloud = if spread > 0.0 {
+        erf(d / (std::f64::consts::SQRT_2 * spread))
+    } else {
+        1.0
+    };
+
+    let mut u = 0.0;
+    for law in laws {
+        if d > law.reach {
+            continue;
+        }
+        let s = source_of(columns, law.source);
+        u += law.sign * law.coupling * EP_F64 * s[i] * s[j] * cloud / d;
+    }
+    return u;
+}
+
+// the force along the line of the pair at distance r, positive pushes apart
+fn pair_force(p: &Pair, r: f64) -> f64 {
+    let mut f = 0.0;
+    for law in force_laws(p.force, p.a, p.b, r) {
+        if let Some(e) = law.expression {
+            let v = evaluate(
+                e,
+                &Input {
+                    distance: r,
+                    range: law.reach,
+                    coupling: law.coupling,
+                    a: p.a,
+                    b: p.b,
+                },
+            );
+            if v.is_finite() {
+                f += v;
+            }
+        } else if law.name != SP
+            && has_component_property(p.a, law.name)
+            && has_component_property(p.b, law.name)
+        {
+            // as central_apply has it
+            let spread = wavepacket_width(p.a).unwrap_or(0.0).powi(2)
+                + wavepacket_width(p.b).unwrap_or(0.0).powi(2);
+            f += central_sign(law.name)
+                * law.coupling
+                * EP_F64
+                * component_property(p.a, law.name)
+                * component_property(p.b, law.name)
+                * smeared(r, spread.sqrt())
+                / (r * r);
+        }
+    }
+    return f;
+}
+
+// minus the work of the pair force from d0 to d1, simpson's rule
+fn work(p: &Pair, d0: f64, d1: f64) -> f64 {
+    if d0 == d1 || d0 == 0.0 || d1 == 0.0 {
+        return 0.0;
+    }
+    let n = WORK_INTERVALS;
+    let h = (d1 - d0) / n as f64;
+    let mut sum = pair_force(p, d0) + pair_force(p, d1);
+    for k in 1..n {
+        let w = if k % 2 == 1 { 4.0 } else { 2.0 };
+        sum += w * pair_force(p, d0 + k as f64 * h);
+    }
+    return -sum * h / 3.0;
+}
+
+fn central_total(columns: &Columns, laws: &Vec<Central>) -> f64 {
+    let mut u = 0.0;
+    for i in 0..columns.len() {
+        for j in i + 1..columns.len() {
+            let d = distance(columns, columns.position(i), columns.position(j));
+            u += central_potential(columns, laws, i, j, d);
+        }
+    }
+    return u;
+}
+
+// root mean square distance from the center of mass, planck lengths
+pub fn gyration(columns: &Columns) -> f64 {
+    let mass: f64 = columns.mass.iter().sum();
+    if mass <= 0.0 {
+        return 0.0;
+    }
+    let mut center = [0.0, 0.0, 0.0];
+    for k in 0..columns.len() {
+        let p = columns.position(k);
+        for x in 0..3 {
+            center[x] += p[x] * columns.mass[k] / mass;
+        }
+    }
+    let mut sum = 0.0;
+    for k in 0..columns.len() {
+        sum += columns.mass[k] * vector_length(sbtr_f64_3(columns.position(k), center)).powi(2);
+    }
+    return (sum / mass).sqrt();
+}
+
+// one move proposed per moving component, positions written back after
+pub fn sweep(s: &mut Sampler, anom: &mut Anomaly) {
+    let laws = central_laws(&anom.force);
+    let mut columns = gather(anom);
+    let n = columns.len();
+    if n == 0 {
+        return;
+    }
+    let mut energy = match s.energy.last() {
+        Some(e) => *e,
+        None => central_total(&columns, &laws),
+    };
+
+    {
+        let index: HashMap<Id, usize> = (0..n).map(|k| (columns.id[k], k)).collect();
+        let mut pair = vec![];
+        collect_pairs(anom, &index, &mut pair);
+        let mut with: Vec<Vec<usize>> = vec![vec![]; n];
+        for (x, p) in pair.iter().enumerate() {
+            with[p.i].push(x);
+            with[p.j].push(x);
+        }
+
+        for _ in 0..n {
+            let k = s.rng.gen_range(0..n);
+            let old = columns.position(k);
+            let new = [
+                old[0] + s.rng.gen_range(-s.step..=s.step),
+                old[1] + s.rng.gen_range(-s.step..=s.step),
+                old[2] + s.rng.gen_range(-s.step..=s.step),
+            ];
+
+            let mut du = 0.0;
+            for j in (0..n).filter(|j| *j != k) {
+                let q = columns.position(j);
+                du += central_potential(&columns, &laws, k, j, distance(&columns, new, q))
+                    - central_potential(&columns, &laws, k, j, distance(&columns, old, q));
+            }
+            for x in &with[k] {
+                let p = &pair[*x];
+                let other = columns.position(if p.i == k { p.j } else { p.i });
+                du += work(
+                    p,
+                    distance(&columns, old, other),
+                    distance(&columns, new, other),
+                );
+            }
+
+            s.proposed += 1;
+            let accept = du <= 0.0
+                || (s.temperature > 0.0 && s.rng.gen_range(0.0..1.0) < (-du / s.temperature).exp());
+            if accept && du.is_finite() {
+                s.accepted += 1;
+                energy += du;
+                columns.px[k] = new[0];
+                columns.py[k] = new[1];
+                columns.pz[k] = new[2];
+            }
+        }
+    }
+
+    scatter(&columns, anom);
+    s.sweeps += 1;
+    s.energy.push(energy);
+    s.gyration.push(gyration(&columns));
+}
+
+pub fn acceptance(s: &Sampler) -> f64 {
+    if s.proposed == 0 {
+        return 0.0;
+    }
+    return s.accepted as f64 / s.proposed as f64;
+}
+
+// mean and variance of the later half of the samples, the first half being the way in
+pub fn equilibrium(sample: &Vec<f64>) -> (f64, f64) {
+    let later = &sample[sample.len() / 2..];
+    if later.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = later.iter().sum::<f64>() / later.len() as f64;
+    let variance = later.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / later.len() as f64;
+    return (mean, variance);
+}
+
+// one line of observables; the heat capacity is var(U) / T^2 in units of k
+pub fn sample_line(s: &Sampler) -> String {
+    let (energy, variance) = equilibrium(&s.energy);
+    let (gyration, _) = equilibrium(&s.gyration);
+    let capacity = if s.temperature > 0.0 {
+        variance / (s.temperature * s.temperature)
+    } else {
+        0.0
+    };
+    return format!(
+        "sweep {} accepted {:.3} energy {:e} MeV capacity {:e} gyration {:e}",
+        s.sweeps,
+        acceptance(s),
+        energy,
+        capacity,
+        gyration
+    );
+}
+
+pub fn sample_step(s: &mut Sampler, anom: &mut Anomaly) {
+    sweep(s, anom);
+    if s.sweeps % s.every == 0 {
+        anom.log.push(sample_line(s));
+    }
+}
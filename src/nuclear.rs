@@ -0,0 +1,156 @@
+use crate::anomaly::{
+    accelerate, component_inertia, component_property, has_component_property, set_inertia,
+    Anomaly, CR, EP_F64, HB_F64, IN0, LS_F64, ML_F64, MS,
+};
+use crate::columns::gather;
+use crate::f64_3::{dd_f64_3, dot_product, mltply_f64_3, sbtr_f64_3, vector_length};
+use crate::query::visit_components_mut;
+
+// the residual strong force between color neutral composites, one pion exchanged:
+//
+//   U(r) = -coupling * EP * |A_a| * |A_b| * exp(-r / range) / r
+//
+// range being hbar c over the pion mass and A the baryon number, quarks counting
+// a third and antiquarks (colors 3 to 5) minus a third. inside the core the two
+// bounce off each other elastically instead
+//
+// a composite feels it when it carries a Yukawa, regroup hands the root's down
+
+#[derive(Clone, Copy, Debug)]
+pub struct Yukawa {
+    pub coupling: f64,
+    // MeV
+    pub mass: f64,
+    // meters
+    pub core: f64,
+}
+
+pub fn yukawa_base() -> Yukawa {
+    return Yukawa {
+        coupling: 1.0,
+        mass: 139.57,
+        core: 0.5e-15,
+    };
+}
+
+// planck lengths
+pub fn yukawa_range(y: &Yukawa) -> f64 {
+    return HB_F64 * LS_F64 / y.mass;
+}
+
+fn colors(anom: &Anomaly) -> [i32; 6] {
+    let mut count = [0; 6];
+    let mut anom = anom;
+    let mut stack = vec![];
+    loop {
+        for c in &anom.component {
+            if has_component_property(c, CR) {
+                count[(component_property(c, CR) as usize) % 6] += 1;
+            }
+        }
+        stack.extend(anom.anomaly.iter());
+        anom = match stack.pop() {
+            Some(a) => a,
+            None => return count,
+        };
+    }
+}
+
+// as many of each color as of its anticolor above it, and colored at all
+pub fn color_neutral(anom: &Anomaly) -> bool {
+    let count = colors(anom);
+    let net = [
+        count[0] - count[3],
+        count[1] - count[4],
+        count[2] - count[5],
+    ];
+    return count.iter().sum::<i32>() > 0 && net[0] == net[1] && net[1] == net[2];
+}
+
+pub fn baryon_number(anom: &Anomaly) -> f64 {
+    let count = colors(anom);
+    let quarks = count[0] + count[1] + count[2];
+    let antiquarks = count[3] + count[4] + count[5];
+    return (quarks - antiquarks) as f64 / 3.0;
+}
+
+// center of mass, velocity of it and the mass, of every moving component below
+fn body(anom: &Anomaly) -> Option<([f64; 3], [f64; 3], f64)> {
+    let columns = gather(anom);
+    let mass: f64 = columns.mass.iter().sum();
+    if mass <= 0.0 {
+        return None;
+    }
+
+    let mut center = [0.0, 0.0, 0.0];
+    let mut momentum = [0.0, 0.0, 0.0];
+    for k in 0..columns.len() {
+        center = dd_f64_3(center, mltply_f64_3(columns.position(k), columns.mass[k]));
+        momentum = dd_f64_3(momentum, mltply_f64_3(columns.inertia(k), columns.mass[k]));
+    }
+    return Some((
+        mltply_f64_3(center, 1.0 / mass),
+        mltply_f64_3(momentum, 1.0 / mass),
+        mass,
+    ));
+}
+
+// every component takes its share of the force, so the composite moves as one
+fn push(anom: &mut Anomaly, force: [f64; 3], mass: f64, time: f64) {
+    visit_components_mut(anom, &mut |_, c| {
+        if has_component_property(c, IN0) && has_component_property(c, MS) {
+            let share = component_property(c, MS) / mass;
+            accelerate(mltply_f64_3(force, share), time, c);
+        }
+    });
+}
+
+fn kick(anom: &mut Anomaly, dv: [f64; 3]) {
+    visit_components_mut(anom, &mut |_, c| {
+        if has_component_property(c, IN0) && has_component_property(c, MS) {
+            set_inertia(dd_f64_3(component_inertia(c), dv), c);
+        }
+    });
+}
+
+pub fn nuclear_interact(a: &mut Anomaly, b: &mut Anomaly, time: f64) {
+    let y = match (a.yukawa.first(), b.yukawa.first()) {
+        (Some(y), Some(_)) => *y,
+        _ => return,
+    };
+    if !color_neutral(a) || !color_neutral(b) {
+        return;
+    }
+    let strength = baryon_number(a).abs() * baryon_number(b).abs();
+    let ((pa, va, ma), (pb, vb, mb)) = match (body(a), body(b)) {
+        (Some(x), Some(z)) => (x, z),
+        _ => return,
+    };
+
+    let r = sbtr_f64_3(pb, pa);
+    let d = vector_length(r);
+    if d == 0.0 {
+        return;
+    }
+    let n = mltply_f64_3(r, 1.0 / d);
+
+    if d < y.core * ML_F64 {
+        let closing = dot_product(sbtr_f64_3(va, vb), n);
+        if closing > 0.0 {
+            let j = 2.0 * ma * mb / (ma + mb) * closing;
+            kick(a, mltply_f64_3(n, -j / ma));
+            kick(b, mltply_f64_3(n, j / mb));
+        }
+        return;
+    }
+
+    let range = yukawa_range(&y);
+    if strength == 0.0 || d > 10.0 * range {
+        return;
+    }
+    // -dU/dr, negative pulls the two together
+    let f =
+        -y.coupling * EP_F64 * strength * (-d / range).exp() * (1.0 / (d * d) + 1.0 / (range * d));
+    push(b, mltply_f64_3(n, f), mb, time);
+    push(a, mltply_f64_3(n, -f), ma, time);
+}
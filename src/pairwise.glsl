@@ -0,0 +1,93 @@
+#version 450
+
+// one invocation per particle, in step units: a velocity of 1 crosses one
+// planck length per step, so light speed is close to 1 and nothing overflows
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) buffer Position {
+    vec4 position[];
+};
+
+layout(set = 0, binding = 1) buffer Velocity {
+    vec4 velocity[];
+};
+
+// law major, law * count + particle
+layout(set = 0, binding = 2) readonly buffer Strength {
+    float strength[];
+};
+
+layout(set = 0, binding = 3) readonly buffer Source {
+    float source[];
+};
+
+layout(set = 0, binding = 4) readonly buffer Reach {
+    float reach[];
+};
+
+// wavepacket widths, 0 for points
+layout(set = 0, binding = 5) readonly buffer Width {
+    float width[];
+};
+
+layout(push_constant) uniform Step {
+    uint count;
+    uint laws;
+    uint stage;
+    float limit;
+} step;
+
+// abramowitz and stegun 7.1.26, as wavepacket.rs
+float erf_approx(float x) {
+    float t = 1.0 / (1.0 + 0.3275911 * abs(x));
+    float p = (((1.061405429 * t - 1.453152027) * t + 1.421413741) * t - 0.284496736) * t;
+    float y = 1.0 - (p + 0.254829592) * t * exp(-x * x);
+    return x < 0.0 ? -y : y;
+}
+
+float smeared(float d, float s) {
+    if (s <= 0.0) {
+        return 1.0;
+    }
+    float x = d / (1.41421356 * s);
+    return erf_approx(x) - 1.12837917 * x * exp(-x * x);
+}
+
+void main() {
+    uint k = gl_GlobalInvocationID.x;
+    if (k >= step.count) {
+        return;
+    }
+
+    if (step.stage == 1) {
+        position[k].xyz += velocity[k].xyz;
+        return;
+    }
+
+    vec3 p = position[k].xyz;
+    vec3 dv = vec3(0.0);
+    for (uint l = 0; l < step.laws; l++) {
+        float s = strength[l * step.count + k];
+        if (s == 0.0) {
+            continue;
+        }
+        for (uint j = 0; j < step.count; j++) {
+            vec3 d = p - position[j].xyz;
+            float d2 = dot(d, d);
+            if (d2 == 0.0 || d2 > reach[l] * reach[l]) {
+                continue;
+            }
+            float spread = sqrt(width[k] * width[k] + width[j] * width[j]);
+            float w = s * source[l * step.count + j] / (d2 * sqrt(d2));
+            dv += d * (w * smeared(sqrt(d2), spread));
+        }
+    }
+
+    vec3 v = velocity[k].xyz + dv;
+    float speed = length(v);
+    if (speed > step.limit) {
+        v *= step.limit / speed;
+    }
+    velocity[k].xyz = v;
+}
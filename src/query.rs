@@ -0,0 +1,271 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::anomaly::{component_property, has_component_property, Anomaly, Component};
+
+// a path walks anomaly indices from the root, then component indices inside
+// the last anomaly reached; an empty component list addresses the anomaly itself
+//
+// "all quarks with charge 2/3 inside the third composite":
+//
+//   components_depth_first(&root, &anomaly_path(vec![2]))
+//       .filter(|(_, c)| property_is(CR, c) && property_near(EC, 2.0 / 3.0, c))
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Path {
+    pub anomaly: Vec<usize>,
+    pub component: Vec<usize>,
+}
+
+pub fn anomaly_path(anomaly: Vec<usize>) -> Path {
+    return Path {
+        anomaly,
+        component: vec![],
+    };
+}
+
+impl Path {
+    pub fn is_component(&self) -> bool {
+        return !self.component.is_empty();
+    }
+
+    pub fn child_anomaly(&self, k: usize) -> Path {
+        let mut p = self.clone();
+        p.anomaly.push(k);
+        return p;
+    }
+
+    pub fn child_component(&self, k: usize) -> Path {
+        let mut p = self.clone();
+        p.component.push(k);
+        return p;
+    }
+
+    // the anomaly a component path lives in
+    pub fn owner(&self) -> Path {
+        return anomaly_path(self.anomaly.clone());
+    }
+
+    pub fn starts_with(&self, other: &Path) -> bool {
+        if other.is_component() {
+            return self.anomaly == other.anomaly && self.component.starts_with(&other.component);
+        }
+        return self.anomaly.starts_with(&other.anomaly);
+    }
+}
+
+// written as /a2/a0/c1, the root is /
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.anomaly.is_empty() && self.component.is_empty() {
+            return write!(f, "/");
+        }
+        for a in &self.anomaly {
+            write!(f, "/a{}", a)?;
+        }
+        for c in &self.component {
+            write!(f, "/c{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_path(text: &str) -> Result<Path, String> {
+    let mut path = Path::default();
+    for step in text.split('/').filter(|s| !s.is_empty()) {
+        let first = step.chars().next().unwrap();
+        let (kind, number) = step.split_at(first.len_utf8());
+        let k = number
+            .parse::<usize>()
+            .map_err(|_| format!("path {}: bad step {}", text, step))?;
+        match kind {
+            "a" if path.component.is_empty() => path.anomaly.push(k),
+            "c" => path.component.push(k),
+            _ => return Err(format!("path {}: bad step {}", text, step)),
+        }
+    }
+
+    return Ok(path);
+}
+
+pub fn anomaly_at<'a>(root: &'a Anomaly, path: &Path) -> Option<&'a Anomaly> {
+    let mut a = root;
+    for k in &path.anomaly {
+        a = a.anomaly.get(*k)?;
+    }
+    return Some(a);
+}
+
+pub fn anomaly_at_mut<'a>(root: &'a mut Anomaly, path: &Path) -> Option<&'a mut Anomaly> {
+    let mut a = root;
+    for k in &path.anomaly {
+        a = a.anomaly.get_mut(*k)?;
+    }
+    return Some(a);
+}
+
+pub fn component_at<'a>(root: &'a Anomaly, path: &Path) -> Option<&'a Component> {
+    let a = anomaly_at(root, path)?;
+    let (first, rest) = path.component.split_first()?;
+    let mut c = a.component.get(*first)?;
+    for k in rest {
+        c = c.component.get(*k)?;
+    }
+    return Some(c);
+}
+
+pub fn component_at_mut<'a>(root: &'a mut Anomaly, path: &Path) -> Option<&'a mut Component> {
+    let a = anomaly_at_mut(root, path)?;
+    let (first, rest) = path.component.split_first()?;
+    let mut c = a.component.get_mut(*first)?;
+    for k in rest {
+        c = c.component.get_mut(*k)?;
+    }
+    return Some(c);
+}
+
+pub struct AnomalyDepthFirst<'a> {
+    stack: Vec<(Path, &'a Anomaly)>,
+}
+
+impl<'a> Iterator for AnomalyDepthFirst<'a> {
+    type Item = (Path, &'a Anomaly);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, a) = self.stack.pop()?;
+        for (k, n) in a.anomaly.iter().enumerate().rev() {
+            self.stack.push((path.child_anomaly(k), n));
+        }
+        return Some((path, a));
+    }
+}
+
+pub struct AnomalyBreadthFirst<'a> {
+    queue: VecDeque<(Path, &'a Anomaly)>,
+}
+
+impl<'a> Iterator for AnomalyBreadthFirst<'a> {
+    type Item = (Path, &'a Anomaly);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, a) = self.queue.pop_front()?;
+        for (k, n) in a.anomaly.iter().enumerate() {
+            self.queue.push_back((path.child_anomaly(k), n));
+        }
+        return Some((path, a));
+    }
+}
+
+// anomalies below (and including) the one at start, paths stay absolute
+pub fn anomalies_depth_first<'a>(root: &'a Anomaly, start: &Path) -> AnomalyDepthFirst<'a> {
+    let stack = match anomaly_at(root, start) {
+        Some(a) => vec![(start.owner(), a)],
+        None => vec![],
+    };
+    return AnomalyDepthFirst { stack };
+}
+
+pub fn anomalies_breadth_first<'a>(root: &'a Anomaly, start: &Path) -> AnomalyBreadthFirst<'a> {
+    let mut queue = VecDeque::new();
+    if let Some(a) = anomaly_at(root, start) {
+        queue.push_back((start.owner(), a));
+    }
+    return AnomalyBreadthFirst { queue };
+}
+
+fn component_tree<'a>(path: Path, c: &'a Component, ret: &mut Vec<(Path, &'a Component)>) {
+    for (k, n) in c.component.iter().enumerate() {
+        ret.push((path.child_component(k), n));
+        component_tree(path.child_component(k), n, ret);
+    }
+}
+
+fn components_of<'a>(path: Path, a: &'a Anomaly) -> Vec<(Path, &'a Component)> {
+    let mut ret = vec![];
+    for (k, c) in a.component.iter().enumerate() {
+        ret.push((path.child_component(k), c));
+        component_tree(path.child_component(k), c, &mut ret);
+    }
+    return ret;
+}
+
+// every component of every anomaly, nested components right after their parent
+pub fn components_depth_first<'a>(
+    root: &'a Anomaly,
+    start: &Path,
+) -> impl Iterator<Item = (Path, &'a Component)> {
+    return anomalies_depth_first(root, start).flat_map(|(p, a)| components_of(p, a));
+}
+
+pub fn components_breadth_first<'a>(
+    root: &'a Anomaly,
+    start: &Path,
+) -> impl Iterator<Item = (Path, &'a Component)> {
+    return anomalies_breadth_first(root, start).flat_map(|(p, a)| components_of(p, a));
+}
+
+pub fn property_is(name: f64, c: &Component) -> bool {
+    return has_component_property(c, name);
+}
+
+pub fn property_near(name: f64, value: f64, c: &Component) -> bool {
+    return has_component_property(c, name)
+        && (component_property(c, name) - value).abs() <= 1e-9 * value.abs().max(1.0);
+}
+
+pub fn property_within(name: f64, low: f64, high: f64, c: &Component) -> bool {
+    if !has_component_property(c, name) {
+        return false;
+    }
+    let v = component_property(c, name);
+    return v >= low && v <= high;
+}
+
+pub fn select_components(
+    root: &Anomaly,
+    start: &Path,
+    predicate: impl Fn(&Component) -> bool,
+) -> Vec<Path> {
+    return components_depth_first(root, start)
+        .filter(|(_, c)| predicate(c))
+        .map(|(p, _)| p)
+        .collect();
+}
+
+pub fn select_anomalies(
+    root: &Anomaly,
+    start: &Path,
+    predicate: impl Fn(&Anomaly) -> bool,
+) -> Vec<Path> {
+    return anomalies_depth_first(root, start)
+        .filter(|(_, a)| predicate(a))
+        .map(|(p, _)| p)
+        .collect();
+}
+
+// depth first, parents before their children
+pub fn visit_anomalies_mut(root: &mut Anomaly, visit: &mut impl FnMut(&Path, &mut Anomaly)) {
+    visit_anomaly(Path::default(), root, visit);
+}
+
+fn visit_anomaly(path: Path, a: &mut Anomaly, visit: &mut impl FnMut(&Path, &mut Anomaly)) {
+    visit(&path, a);
+    for (k, n) in a.anomaly.iter_mut().enumerate() {
+        visit_anomaly(path.child_anomaly(k), n, visit);
+    }
+}
+
+pub fn visit_components_mut(root: &mut Anomaly, visit: &mut impl FnMut(&Path, &mut Component)) {
+    visit_anomalies_mut(root, &mut |path, a| {
+        for (k, c) in a.component.iter_mut().enumerate() {
+            visit_component(path.child_component(k), c, visit);
+        }
+    });
+}
+
+fn visit_component(path: Path, c: &mut Component, visit: &mut impl FnMut(&Path, &mut Component)) {
+    visit(&path, c);
+    for (k, n) in c.component.iter_mut().enumerate() {
+        visit_component(path.child_component(k), n, visit);
+    }
+}
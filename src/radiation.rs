@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use crate::anomaly::{photon, Anomaly, AF_F64, HB_F64, LS_F64};
+use crate::arena::Id;
+use crate::columns::{gather, Columns};
+use crate::decay::beta_of;
+use crate::f64_3::{dot_product, mltply_f64_3, nrmlz_f64_3, sbtr_f64_3, vector_length};
+
+// accelerated charges radiate, larmor's power being
+//
+//   P = 2/3 * Z^2 * alpha * hbar * a^2 / c^2
+//
+// with a the change of velocity over the whole progress, whatever caused it.
+// without photons the energy is taken from the kinetic energy of the charge by
+// slowing it along its velocity. with photons on, what a component radiated is
+// kept until it reaches the threshold and then leaves as a photon along the
+// velocity, carrying the energy E and the momentum E / c; the charge recoils by
+// exactly that momentum, so momentum is conserved, while the part of E its own
+// kinetic energy does not cover came from whatever accelerated it. photons are
+// not charged, so they never radiate themselves. energies and momenta in MeV
+
+pub struct Radiation {
+    pub photons: bool,
+    // MeV, the least a photon carries
+    pub threshold: f64,
+    // MeV, everything radiated so far
+    pub radiated: f64,
+    pub emitted: u64,
+    // MeV radiated by a component and not yet emitted
+    pending: HashMap<Id, f64>,
+}
+
+pub fn radiation_base(photons: bool, threshold: f64) -> Radiation {
+    return Radiation {
+        photons,
+        threshold: threshold.max(0.0),
+        radiated: 0.0,
+        emitted: 0,
+        pending: HashMap::new(),
+    };
+}
+
+// velocities of the charged moving components, before anything acts on them
+pub fn charged_velocities(anom: &Anomaly) -> HashMap<Id, [f64; 3]> {
+    let columns = gather(anom);
+    return (0..columns.len())
+        .filter(|k| columns.charge[*k] != 0.0)
+        .map(|k| (columns.id[k], columns.inertia(k)))
+        .collect();
+}
+
+// MeV per second
+pub fn larmor_power(charge: f64, acceleration: f64) -> f64 {
+    return 2.0 / 3.0 * charge * charge * AF_F64 * HB_F64 * acceleration * acceleration
+        / (LS_F64 * LS_F64);
+}
+
+// relativistic momentum of a column, MeV
+fn momentum(mass: f64, velocity: [f64; 3]) -> [f64; 3] {
+    let beta = beta_of(velocity);
+    let gamma = 1.0 / (1.0 - dot_product(beta, beta)).sqrt();
+    return mltply_f64_3(beta, gamma * mass);
+}
+
+// the velocity of column k set to carry momentum p
+fn recoil(columns: &mut Columns, k: usize, p: [f64; 3]) {
+    let mass = columns.mass[k];
+    let v = mltply_f64_3(p, LS_F64 / (mass * mass + dot_product(p, p)).sqrt());
+    columns.ix[k] = v[0];
+    columns.iy[k] = v[1];
+    columns.iz[k] = v[2];
+}
+
+// drains the columns for the time they were stepped, the photons come back to be added
+pub fn radiate(
+    r: &mut Radiation,
+    before: &HashMap<Id, [f64; 3]>,
+    columns: &mut Columns,
+    time: f64,
+) -> Vec<Anomaly> {
+    let mut ret = vec![];
+    if time <= 0.0 {
+        return ret;
+    }
+
+    for k in 0..columns.len() {
+        let v0 = match before.get(&columns.id[k]) {
+            Some(v) => *v,
+            None => continue,
+        };
+        let v = columns.inertia(k);
+        let speed = vector_length(v);
+        let kinetic = 0.5 * columns.mass[k] * speed * speed / (LS_F64 * LS_F64);
+        if kinetic <= 0.0 {
+            continue;
+        }
+
+        let a = vector_length(sbtr_f64_3(v, v0)) / time;
+        let lost = (larmor_power(columns.charge[k], a) * time).min(kinetic);
+        if lost <= 0.0 {
+            continue;
+        }
+        r.radiated += lost;
+
+        if !r.photons {
+            let s = (1.0 - lost / kinetic).sqrt();
+            columns.ix[k] *= s;
+            columns.iy[k] *= s;
+            columns.iz[k] *= s;
+            continue;
+        }
+        let pending = r.pending.entry(columns.id[k]).or_insert(0.0);
+        *pending += lost;
+        if *pending < r.threshold {
+            continue;
+        }
+        // never more than the charge has, the rest waits for the next photon
+        let direction = nrmlz_f64_3(v);
+        let p = momentum(columns.mass[k], v);
+        let energy = pending.min(vector_length(p));
+        recoil(columns, k, sbtr_f64_3(p, mltply_f64_3(direction, energy)));
+        ret.push(photon(
+            columns.position(k),
+            mltply_f64_3(direction, LS_F64),
+            energy,
+        ));
+        r.emitted += 1;
+        *pending -= energy;
+    }
+
+    return ret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, component_inertia, component_property, e, vacuum, EN};
+    use crate::arena::establish;
+    use crate::f64_3::dd_f64_3;
+
+    // an electron that went from rest to 0.3 c within the time
+    fn kicked() -> (HashMap<Id, [f64; 3]>, Columns) {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e([0.0; 3], [0.3 * LS_F64, 0.0, 0.0], true));
+        let columns = gather(&root);
+        let before = charged_velocities(&root)
+            .into_keys()
+            .map(|id| (id, [0.0; 3]))
+            .collect();
+        return (before, columns);
+    }
+
+    fn kinetic(columns: &Columns) -> f64 {
+        let v = vector_length(columns.inertia(0)) / LS_F64;
+        return 0.5 * columns.mass[0] * v * v;
+    }
+
+    #[test]
+    fn radiated_energy_is_the_kinetic_energy_lost() {
+        let (before, mut columns) = kicked();
+        let mut r = radiation_base(false, 0.0);
+        let start = kinetic(&columns);
+        let photons = radiate(&mut r, &before, &mut columns, 1e-22);
+
+        let a = 0.3 * LS_F64 / 1e-22;
+        let larmor = larmor_power(columns.charge[0], a) * 1e-22;
+        assert!(photons.is_empty());
+        assert!(r.radiated > 0.0 && (r.radiated - larmor).abs() <= 1e-12 * larmor);
+        assert!((start - kinetic(&columns) - r.radiated).abs() <= 1e-9 * r.radiated);
+    }
+
+    #[test]
+    fn photons_carry_the_momentum_the_charge_lost() {
+        let (before, mut columns) = kicked();
+        let mut r = radiation_base(true, 1e-6);
+        let start = momentum(columns.mass[0], columns.inertia(0));
+        let photons = radiate(&mut r, &before, &mut columns, 1e-22);
+        assert_eq!(photons.len(), 1);
+
+        let c = &photons[0].component[0];
+        let energy = component_property(c, EN);
+        let carried = mltply_f64_3(nrmlz_f64_3(component_inertia(c)), energy);
+        let total = dd_f64_3(momentum(columns.mass[0], columns.inertia(0)), carried);
+        assert!((energy - r.radiated).abs() <= 1e-12 * energy);
+        assert!(vector_length(sbtr_f64_3(total, start)) <= 1e-9 * vector_length(start));
+        assert!(vector_length(columns.inertia(0)) < 0.3 * LS_F64);
+    }
+}
@@ -0,0 +1,475 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::anomaly::{
+    component_inertia, component_position, component_property, has_component_property, Anomaly,
+    Component, CR, EN, LS_F64, MS,
+};
+use crate::arena::{anomaly_by_id, insert_by_id, remove_by_id, Id};
+use crate::conservation::{begin, end};
+use crate::decay::{beta_of, boost, make_species, phase_space, species_named, species_of, SPECIES};
+use crate::f64_3::{dd_f64_3, dot_product, mltply_f64_3, sbtr_f64_3, vector_length};
+use crate::force::share_force;
+
+// two particles of the same parent that come closer than the reach while
+// closing in may react: every rule for their species whose threshold the
+// energy in their center of mass frame clears is open, one of them is picked
+// by weight and the two are replaced by its products. the products share the
+// four momentum of the pair, spread over the phase space in the center of
+// mass frame and boosted back, so energy and momentum are kept
+//
+// a rule is never open below the masses of its products. colored reactants
+// have to be a color and its anticolor, colored products come as a pair of
+// a fresh color and its anticolor. energies in MeV, the reach in planck lengths
+
+// thresholds are the product masses, but quarks only come out bound, so
+// from two photons not below a pair of charged pions. weights are only ever
+// compared between rules of the same reactants, as rough cross sections: a
+// lepton pair through one virtual photon makes a twentieth of the two photon
+// rate for electrons and a tenth for muons, quark pairs from two photons
+// scale with 3 Q^4 against a lepton pair
+
+pub struct Rule {
+    pub reactant: [&'static str; 2],
+    pub product: &'static [&'static str],
+    // MeV of center of mass energy, never below the masses of the products
+    pub threshold: f64,
+    // relative to the other rules of the same reactants
+    pub weight: f64,
+}
+
+pub static RULES: [Rule; 17] = [
+    Rule {
+        reactant: ["e-", "e+"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["e-", "e+"],
+        product: &["mu-", "mu+"],
+        threshold: 211.316,
+        weight: 0.05,
+    },
+    Rule {
+        reactant: ["e-", "e+"],
+        product: &["tau-", "tau+"],
+        threshold: 3553.72,
+        weight: 0.05,
+    },
+    Rule {
+        reactant: ["mu-", "mu+"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["mu-", "mu+"],
+        product: &["e-", "e+"],
+        threshold: 1.022,
+        weight: 0.1,
+    },
+    Rule {
+        reactant: ["tau-", "tau+"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["gamma", "gamma"],
+        product: &["e-", "e+"],
+        threshold: 1.022,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["gamma", "gamma"],
+        product: &["mu-", "mu+"],
+        threshold: 211.316,
+        weight: 0.1,
+    },
+    Rule {
+        reactant: ["gamma", "gamma"],
+        product: &["u", "anti-u"],
+        threshold: 279.14,
+        weight: 0.6,
+    },
+    Rule {
+        reactant: ["gamma", "gamma"],
+        product: &["d", "anti-d"],
+        threshold: 279.14,
+        weight: 0.04,
+    },
+    Rule {
+        reactant: ["pi+", "pi-"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["u", "anti-u"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["d", "anti-d"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["s", "anti-s"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["c", "anti-c"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["b", "anti-b"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+    Rule {
+        reactant: ["t", "anti-t"],
+        product: &["gamma", "gamma"],
+        threshold: 0.0,
+        weight: 1.0,
+    },
+];
+
+pub struct Collision {
+    // s, the clock of the root
+    pub clock: f64,
+    pub reactant: [(Id, &'static str); 2],
+    pub product: Vec<(Id, &'static str)>,
+    // MeV in the center of mass frame
+    pub energy: f64,
+    pub position: [f64; 3],
+}
+
+pub struct Reaction {
+    pub reach: f64,
+    // reactions not yet taken into a snapshot
+    pub collision: Vec<Collision>,
+    rng: StdRng,
+}
+
+pub fn reaction_base(reach: f64, seed: u64) -> Reaction {
+    return Reaction {
+        reach,
+        collision: vec![],
+        rng: StdRng::seed_from_u64(seed),
+    };
+}
+
+// energy and momentum, MeV; massless components carry their energy in EN
+pub fn four_momentum(c: &Component) -> (f64, [f64; 3]) {
+    let v = component_inertia(c);
+    let mass = component_property(c, MS);
+    if mass == 0.0 {
+        let e = if has_component_property(c, EN) {
+            component_property(c, EN)
+        } else {
+            0.0
+        };
+        let speed = vector_length(v);
+        if speed == 0.0 {
+            return (e, [0.0, 0.0, 0.0]);
+        }
+        return (e, mltply_f64_3(v, e / speed));
+    }
+    let beta = beta_of(v);
+    let gamma = 1.0 / (1.0 - dot_product(beta, beta)).sqrt();
+    return (gamma * mass, mltply_f64_3(beta, gamma * mass));
+}
+
+fn color_of(c: &Component) -> Option<f64> {
+    if has_component_property(c, CR) {
+        return Some(component_property(c, CR));
+    }
+    return None;
+}
+
+// the rules open to species a and b at center of mass energy e, reactants in rule order
+fn open_rules(a: usize, b: usize, e: f64) -> Vec<&'static Rule> {
+    let (a, b) = (SPECIES[a].name, SPECIES[b].name);
+    return RULES
+        .iter()
+        .filter(|r| {
+            (r.reactant == [a, b] || r.reactant == [b, a])
+                && e >= r.threshold
+                && e > r
+                    .product
+                    .iter()
+                    .filter_map(|n| species_named(n))
+                    .map(|k| SPECIES[k].mass)
+                    .sum::<f64>()
+        })
+        .collect();
+}
+
+fn pick<'a>(rule: &Vec<&'a Rule>, rng: &mut StdRng) -> Option<&'a Rule> {
+    let total: f64 = rule.iter().map(|r| r.weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut x = rng.gen_range(0.0..total);
+    for r in rule {
+        if x < r.weight {
+            return Some(r);
+        }
+        x -= r.weight;
+    }
+    return rule.last().copied();
+}
+
+// a particle that may react, as it is found
+struct Reactant {
+    id: Id,
+    species: usize,
+    position: [f64; 3],
+    velocity: [f64; 3],
+    color: Option<f64>,
+}
+
+// the particles below one parent, parent by parent, rigid composites left alone
+fn reactants(anom: &Anomaly, ret: &mut Vec<(Id, Vec<Reactant>)>) {
+    if !anom.rigid.is_empty() {
+        return;
+    }
+    let mut here = vec![];
+    for a in &anom.anomaly {
+        match species_of(a) {
+            Some(k) => {
+                let c = &a.component[0];
+                here.push(Reactant {
+                    id: a.id,
+                    species: k,
+                    position: component_position(c),
+                    velocity: component_inertia(c),
+                    color: color_of(c),
+                });
+            }
+            None => reactants(a, ret),
+        }
+    }
+    if here.len() > 1 {
+        ret.push((anom.id, here));
+    }
+}
+
+// a color and its anticolor, or no color on either
+fn colors_meet(a: Option<f64>, b: Option<f64>) -> bool {
+    return match (a, b) {
+        (None, None) => true,
+        (Some(x), Some(y)) => (x as i32 + 3) % 6 == y as i32,
+        _ => false,
+    };
+}
+
+pub fn reaction_step(r: &mut Reaction, root: &mut Anomaly) {
+    let mut group = vec![];
+    reactants(root, &mut group);
+
+    for (parent, here) in group {
+        let mut gone = vec![false; here.len()];
+        for i in 0..here.len() {
+            for j in i + 1..here.len() {
+                if gone[i] || gone[j] {
+                    continue;
+                }
+                let (a, b) = (&here[i], &here[j]);
+                let apart = sbtr_f64_3(b.position, a.position);
+                let closing = dot_product(sbtr_f64_3(b.velocity, a.velocity), apart) < 0.0;
+                if !closing || vector_length(apart) > r.reach || !colors_meet(a.color, b.color) {
+                    continue;
+                }
+                if react(r, root, parent, a, b) {
+                    gone[i] = true;
+                    gone[j] = true;
+                }
+            }
+        }
+    }
+}
+
+fn react(r: &mut Reaction, root: &mut Anomaly, parent: Id, a: &Reactant, b: &Reactant) -> bool {
+    let ca = match anomaly_by_id(root, a.id) {
+        Some(x) => four_momentum(&x.component[0]),
+        None => return false,
+    };
+    let cb = match anomaly_by_id(root, b.id) {
+        Some(x) => four_momentum(&x.component[0]),
+        None => return false,
+    };
+    let e = ca.0 + cb.0;
+    let p = dd_f64_3(ca.1, cb.1);
+    let s = e * e - dot_product(p, p);
+    if s <= 0.0 {
+        return false;
+    }
+    let energy = s.sqrt();
+
+    let rule = match pick(&open_rules(a.species, b.species, energy), &mut r.rng) {
+        Some(x) => x,
+        None => return false,
+    };
+    let kind: Vec<usize> = rule
+        .product
+        .iter()
+        .filter_map(|n| species_named(n))
+        .collect();
+    if kind.len() != rule.product.len() {
+        return false;
+    }
+    let mass: Vec<f64> = kind.iter().map(|k| SPECIES[*k].mass).collect();
+
+    begin(root);
+    let old = match remove_by_id(root, a.id) {
+        Some(x) => x,
+        None => {
+            end(root, "no reaction");
+            return false;
+        }
+    };
+    remove_by_id(root, b.id);
+
+    let position = mltply_f64_3(dd_f64_3(a.position, b.position), 0.5);
+    let beta = mltply_f64_3(p, 1.0 / e);
+    let color = r.rng.gen_range(0..3) as f64;
+    let mut product = vec![];
+    for (k, (pe, pp)) in kind.iter().zip(phase_space(energy, &mass, &mut r.rng)) {
+        let (pe, pp) = boost(pe, pp, beta);
+        let anti = SPECIES[*k].color < 0;
+        let mut x = make_species(
+            *k,
+            position,
+            mltply_f64_3(pp, LS_F64 / pe),
+            pe,
+            if anti { color + 3.0 } else { color },
+            r.rng.gen_range(0..2) == 0,
+        );
+        x.force = old.force.iter().map(share_force).collect();
+        if let Some(n) = insert_by_id(root, parent, x) {
+            product.push((n, SPECIES[*k].name));
+        }
+    }
+
+    let collision = Collision {
+        clock: root.clock,
+        reactant: [
+            (a.id, SPECIES[a.species].name),
+            (b.id, SPECIES[b.species].name),
+        ],
+        product,
+        energy,
+        position,
+    };
+    end(root, &collision_line(&collision));
+    r.collision.push(collision);
+    return true;
+}
+
+// one line per reaction: the clock, the reactants, the products and the energy
+pub fn collision_line(c: &Collision) -> String {
+    let product: Vec<&str> = c.product.iter().map(|(_, n)| *n).collect();
+    return format!(
+        "reaction at {:e} s: {} {} -> {} at {:e} MeV",
+        c.clock,
+        c.reactant[0].1,
+        c.reactant[1].1,
+        product.join(" "),
+        c.energy
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, vacuum};
+    use crate::arena::establish;
+    use crate::decay::Species;
+    use crate::query::{anomalies_depth_first, Path};
+
+    fn species(name: &str) -> &'static Species {
+        return &SPECIES[species_named(name).unwrap()];
+    }
+
+    // charge, baryon number, color and the three lepton numbers of some species
+    fn numbers(name: &[&str]) -> [f64; 6] {
+        let mut ret = [0.0; 6];
+        for s in name.iter().map(|n| species(n)) {
+            ret[0] += s.charge;
+            ret[1] += s.baryon;
+            ret[2] += s.color as f64;
+            for x in 0..3 {
+                ret[3 + x] += s.lepton[x] as f64;
+            }
+        }
+        return ret;
+    }
+
+    #[test]
+    fn rules_keep_charge_baryon_lepton_numbers_and_color() {
+        for r in RULES.iter() {
+            assert_eq!(
+                numbers(&r.reactant),
+                numbers(r.product),
+                "{:?} -> {:?}",
+                r.reactant,
+                r.product
+            );
+            let mass: f64 = r.product.iter().map(|n| species(n).mass).sum();
+            assert!(r.threshold >= mass && r.weight > 0.0, "{:?}", r.product);
+        }
+    }
+
+    // the pair of every rule met head on above its threshold
+    #[test]
+    fn reactions_keep_energy_and_momentum() {
+        for (seed, r) in RULES.iter().enumerate() {
+            let mut root = vacuum();
+            establish(&mut root);
+            let top = root.id;
+            let mass: f64 = r.reactant.iter().map(|n| species(n).mass).sum();
+            let energy = 2.0 * r.threshold.max(mass).max(1.0);
+            for (side, name) in [(1.0, r.reactant[0]), (-1.0, r.reactant[1])] {
+                let k = species_named(name).unwrap();
+                let m = SPECIES[k].mass;
+                // each carries half the center of mass energy towards the other
+                let p = ((energy / 2.0).powi(2) - m * m).sqrt();
+                let speed = LS_F64 * p / (energy / 2.0);
+                let color = if SPECIES[k].color < 0 { 3.0 } else { 0.0 };
+                let a = make_species(
+                    k,
+                    [-side, 0.0, 0.0],
+                    [side * speed, 0.0, 0.0],
+                    energy / 2.0,
+                    color,
+                    true,
+                );
+                add_particle_by(&mut root, top, a);
+            }
+
+            let mut reaction = reaction_base(10.0, seed as u64);
+            reaction_step(&mut reaction, &mut root);
+            assert_eq!(reaction.collision.len(), 1, "{:?}", r.reactant);
+
+            let mut e = 0.0;
+            let mut p = [0.0; 3];
+            for (_, a) in anomalies_depth_first(&root, &Path::default()).skip(1) {
+                let (ae, ap) = four_momentum(&a.component[0]);
+                e += ae;
+                p = dd_f64_3(p, ap);
+            }
+            assert!((e - energy).abs() <= 1e-6 * energy, "{:?}", r.reactant);
+            assert!(vector_length(p) <= 1e-6 * energy, "{:?}", r.reactant);
+        }
+    }
+}
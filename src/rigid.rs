@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use crate::anomaly::{Anomaly, LS_F64};
+use crate::arena::Id;
+use crate::columns::{gather, Columns};
+use crate::ewald::nearest_image;
+use crate::f64_3::{cross_product, dd_f64_3, mltply_f64_3, nrmlz_f64_3, sbtr_f64_3, vector_length};
+use crate::query::visit_anomalies_mut;
+
+// a composite in rigid mode moves as one body: every step the members are
+// first left to move freely, then what they picked up on top of the body's own
+// motion is summed into an impulse and a torque impulse, and the members are
+// put back on the body at its new place and orientation
+//
+// momentum in MeV planck lengths per second, inertia in MeV planck lengths squared
+
+pub struct Rigid {
+    pub center: [f64; 3],
+    pub velocity: [f64; 3],
+    pub angular: [f64; 3],
+    // w x y z, body to world
+    pub orientation: [f64; 4],
+    pub mass: f64,
+    inertia: [[f64; 3]; 3],
+    member: Vec<(Id, f64, [f64; 3])>,
+}
+
+type Matrix = [[f64; 3]; 3];
+
+fn apply(m: &Matrix, v: [f64; 3]) -> [f64; 3] {
+    return [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ];
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            for k in 0..3 {
+                m[i][j] += a[i][k] * b[k][j];
+            }
+        }
+    }
+    return m;
+}
+
+fn transpose(a: &Matrix) -> Matrix {
+    let mut m = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            m[i][j] = a[j][i];
+        }
+    }
+    return m;
+}
+
+// a body on a line has no inertia about it, a little of the trace keeps it invertible
+fn invert(a: &Matrix) -> Matrix {
+    let mut a = *a;
+    let trace = a[0][0] + a[1][1] + a[2][2];
+    let det = |a: &Matrix| {
+        a[0][0] * (a[1][1] * a[2][2] - a[1][2] * a[2][1])
+            - a[0][1] * (a[1][0] * a[2][2] - a[1][2] * a[2][0])
+            + a[0][2] * (a[1][0] * a[2][1] - a[1][1] * a[2][0])
+    };
+    if det(&a).abs() <= 1e-12 * trace.powi(3).abs() {
+        for i in 0..3 {
+            a[i][i] += 1e-6 * trace;
+        }
+    }
+    let d = det(&a);
+    if d == 0.0 {
+        return [[0.0; 3]; 3];
+    }
+
+    let mut m = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            let (i1, i2) = ((j + 1) % 3, (j + 2) % 3);
+            let (j1, j2) = ((i + 1) % 3, (i + 2) % 3);
+            m[i][j] = (a[i1][j1] * a[i2][j2] - a[i1][j2] * a[i2][j1]) / d;
+        }
+    }
+    return m;
+}
+
+pub fn rotation(q: [f64; 4]) -> Matrix {
+    let [w, x, y, z] = q;
+    return [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+        ],
+        [
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+        ],
+        [
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ];
+}
+
+// turns q by the angular velocity over time, exactly for a constant w
+fn turn(q: [f64; 4], w: [f64; 3], time: f64) -> [f64; 4] {
+    let rate = vector_length(w);
+    if rate == 0.0 {
+        return q;
+    }
+    let half = rate * time / 2.0;
+    let axis = nrmlz_f64_3(w);
+    let (s, c) = (half.sin(), half.cos());
+    let d = [c, axis[0] * s, axis[1] * s, axis[2] * s];
+
+    let r = [
+        d[0] * q[0] - d[1] * q[1] - d[2] * q[2] - d[3] * q[3],
+        d[0] * q[1] + d[1] * q[0] + d[2] * q[3] - d[3] * q[2],
+        d[0] * q[2] - d[1] * q[3] + d[2] * q[0] + d[3] * q[1],
+        d[0] * q[3] + d[1] * q[2] - d[2] * q[1] + d[3] * q[0],
+    ];
+    let n = (r[0] * r[0] + r[1] * r[1] + r[2] * r[2] + r[3] * r[3]).sqrt();
+    return [r[0] / n, r[1] / n, r[2] / n, r[3] / n];
+}
+
+fn inertia_of(member: &Vec<(Id, f64, [f64; 3])>) -> Matrix {
+    let mut m = [[0.0; 3]; 3];
+    for (_, mass, r) in member {
+        let r2 = r[0] * r[0] + r[1] * r[1] + r[2] * r[2];
+        for i in 0..3 {
+            for j in 0..3 {
+                let delta = if i == j { r2 } else { 0.0 };
+                m[i][j] += mass * (delta - r[i] * r[j]);
+            }
+        }
+    }
+    return m;
+}
+
+// the body frame is the world frame at the moment the composite is made rigid;
+// in a periodic box of the given size the members are taken where they are
+// nearest to the first one
+pub fn make_rigid(anom: &mut Anomaly, size: Option<[f64; 3]>) {
+    let columns = gather(anom);
+    let mass: f64 = columns.mass.iter().sum();
+    if columns.len() < 2 || mass <= 0.0 {
+        return;
+    }
+    let near = |d: [f64; 3]| match size {
+        Some(s) => nearest_image(d, s),
+        None => d,
+    };
+
+    let first = columns.position(0);
+    let mut center = [0.0, 0.0, 0.0];
+    let mut momentum = [0.0, 0.0, 0.0];
+    for k in 0..columns.len() {
+        let r = near(sbtr_f64_3(columns.position(k), first));
+        center = dd_f64_3(center, mltply_f64_3(r, columns.mass[k]));
+        momentum = dd_f64_3(momentum, mltply_f64_3(columns.inertia(k), columns.mass[k]));
+    }
+    center = dd_f64_3(first, mltply_f64_3(center, 1.0 / mass));
+    let velocity = mltply_f64_3(momentum, 1.0 / mass);
+
+    let mut member = vec![];
+    let mut angular = [0.0, 0.0, 0.0];
+    for k in 0..columns.len() {
+        let r = near(sbtr_f64_3(columns.position(k), center));
+        let u = sbtr_f64_3(columns.inertia(k), velocity);
+        angular = dd_f64_3(angular, cross_product(r, mltply_f64_3(u, columns.mass[k])));
+        member.push((columns.id[k], columns.mass[k], r));
+    }
+
+    anom.rigid = vec![Rigid {
+        center,
+        velocity,
+        angular,
+        orientation: [1.0, 0.0, 0.0, 0.0],
+        mass,
+        inertia: inertia_of(&member),
+        member,
+    }];
+}
+
+pub fn angular_velocity(body: &Rigid) -> [f64; 3] {
+    let r = rotation(body.orientation);
+    let world = multiply(&multiply(&r, &body.inertia), &transpose(&r));
+    return apply(&invert(&world), body.angular);
+}
+
+// columns after a free step in, columns on their bodies out
+pub fn rigid_columns(root: &mut Anomaly, columns: &mut Columns, time: f64) {
+    let index: HashMap<Id, usize> = (0..columns.len()).map(|k| (columns.id[k], k)).collect();
+
+    visit_anomalies_mut(root, &mut |_, a| {
+        for body in a.rigid.iter_mut() {
+            rigid_step(body, columns, &index, time);
+        }
+    });
+}
+
+fn rigid_step(body: &mut Rigid, columns: &mut Columns, index: &HashMap<Id, usize>, time: f64) {
+    let r = rotation(body.orientation);
+    let w = angular_velocity(body);
+
+    let mut impulse = [0.0, 0.0, 0.0];
+    let mut torque = [0.0, 0.0, 0.0];
+    for (id, mass, offset) in &body.member {
+        let k = match index.get(id) {
+            Some(k) => *k,
+            None => continue,
+        };
+        let arm = apply(&r, *offset);
+        let carried = dd_f64_3(body.velocity, cross_product(w, arm));
+        let kick = mltply_f64_3(sbtr_f64_3(columns.inertia(k), carried), *mass);
+        impulse = dd_f64_3(impulse, kick);
+        torque = dd_f64_3(torque, cross_product(arm, kick));
+    }
+
+    body.velocity = dd_f64_3(body.velocity, mltply_f64_3(impulse, 1.0 / body.mass));
+    if vector_length(body.velocity) > LS_F64 {
+        body.velocity = mltply_f64_3(nrmlz_f64_3(body.velocity), LS_F64);
+    }
+    body.angular = dd_f64_3(body.angular, torque);
+    body.center = dd_f64_3(body.center, mltply_f64_3(body.velocity, time));
+    body.orientation = turn(body.orientation, angular_velocity(body), time);
+
+    let r = rotation(body.orientation);
+    let w = angular_velocity(body);
+    for (id, _, offset) in &body.member {
+        let k = match index.get(id) {
+            Some(k) => *k,
+            None => continue,
+        };
+        let arm = apply(&r, *offset);
+        let p = dd_f64_3(body.center, arm);
+        let v = dd_f64_3(body.velocity, cross_product(w, arm));
+        columns.px[k] = p[0];
+        columns.py[k] = p[1];
+        columns.pz[k] = p[2];
+        columns.ix[k] = v[0];
+        columns.iy[k] = v[1];
+        columns.iz[k] = v[2];
+    }
+}
@@ -0,0 +1,541 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::sync::Arc;
+
+use crate::anomaly::{add_particle_by, e, force_base, q, vacuum, Anomaly, Force, Property, LS_F64};
+use crate::arena::{establish, Id};
+use crate::cluster::{watch_base, Watch};
+use crate::conservation::{validator_base, validator_of, Validator};
+use crate::constraint::parse_constraint;
+use crate::decay::{decay_base, make_species, species_named, Decay, SPECIES};
+use crate::distribution::deserialize;
+use crate::ensemble::{ensemble_base, Ensemble, Sweep};
+use crate::ewald::{ewald_base, periodic_base, Periodic};
+use crate::expression::property_code;
+use crate::f64_3::{gen_f64_3, mltply_f64_3, nrmlz_f64_3};
+use crate::field::{point_charge, solenoid, uniform, Field};
+use crate::force::{parse_force, share_force};
+use crate::monte_carlo::{sampler_base, Sampler};
+use crate::nuclear::{yukawa_base, Yukawa};
+use crate::query::visit_anomalies_mut;
+use crate::radiation::{radiation_base, Radiation};
+use crate::reaction::{reaction_base, Reaction};
+use crate::thermostat::{thermostat_base, thermostat_kind, Thermostat};
+use crate::wavepacket::make_wavepacket;
+
+// a scenario file is a list of lines, a setting name followed by its numbers:
+//
+//   electrons 10
+//   quarks 10
+//   spread 69
+//   distribution shell 2 12
+//   field uniform ex ey ez bx by bz [frequency phase]
+//   field solenoid cx cy cz ax ay az radius b [frequency phase]
+//   field charge cx cy cz charge [frequency phase]
+//   force EC=1 range 1e-15 : coupling * a.EC * b.EC / r^2   (see force.rs)
+//   wavepacket sigma   (planck lengths, see wavepacket.rs)
+//   cluster linking every [regroup|rigid]   (planck lengths, steps, see cluster.rs)
+//   bond 0 1 length stiffness   (particles by number, see constraint.rs)
+//   distance 0 1 length
+//   angle 0 1 2 degrees stiffness
+//   sample temperature step every   (MeV, planck lengths, sweeps, see monte_carlo.rs)
+//   nuclear [coupling pion_mass core]   (MeV, meters, for regrouped composites, see nuclear.rs)
+//   thermostat rescale|berendsen|langevin|nose-hoover temperature [tau]   (MeV, s, see thermostat.rs)
+//   radiation [photons threshold]   (MeV, see radiation.rs)
+//   spawn mu- 10   (at rest, after the electrons and quarks, see decay.rs)
+//   decay
+//   reactions reach   (planck lengths, see reaction.rs)
+//   validate   (see conservation.rs)
+//   periodic lx ly lz [tolerance]   (planck lengths, coulomb by ewald sums, see ewald.rs)
+//   coupling EC 0.01   (every force domain value of that property)
+//   seed 7
+//   ensemble runs steps [file.csv]   (see ensemble.rs)
+//   sweep electrons 5 10 20
+//   sweep coupling EC 0.005 0.01
+//
+// everything after # is ignored
+
+pub struct Scenario {
+    pub electrons: u32,
+    pub quarks: u32,
+    pub spread: f64,
+    pub field: Vec<Field>,
+    pub distribution: String,
+    pub force: Vec<String>,
+    pub cluster: Vec<Watch>,
+    pub constraint: Vec<String>,
+    pub yukawa: Vec<Yukawa>,
+    // sampled instead of stepped when set
+    pub sampler: Vec<Sampler>,
+    // 0 keeps particles points
+    pub wavepacket: f64,
+    pub coupling: Vec<Property>,
+    pub thermostat: Vec<Thermostat>,
+    pub radiation: Vec<Radiation>,
+    // species by number in SPECIES and how many
+    pub spawn: Vec<(usize, u32)>,
+    pub decay: Vec<Decay>,
+    pub reaction: Vec<Reaction>,
+    pub validator: Vec<Validator>,
+    pub periodic: Vec<Periodic>,
+    // random placement unless set
+    pub seed: Vec<u64>,
+    pub ensemble: Vec<Ensemble>,
+    pub sweep: Vec<Sweep>,
+}
+
+pub fn scenario_base() -> Scenario {
+    return Scenario {
+        electrons: 10,
+        quarks: 10,
+        spread: 69.0,
+        field: vec![],
+        distribution: "particular".to_string(),
+        force: vec![],
+        cluster: vec![],
+        constraint: vec![],
+        yukawa: vec![],
+        sampler: vec![],
+        wavepacket: 0.0,
+        coupling: vec![],
+        thermostat: vec![],
+        radiation: vec![],
+        spawn: vec![],
+        decay: vec![],
+        reaction: vec![],
+        validator: vec![],
+        periodic: vec![],
+        seed: vec![],
+        ensemble: vec![],
+        sweep: vec![],
+    };
+}
+
+pub fn read_scenario(path: &str) -> Result<Scenario, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_scenario(&text);
+}
+
+pub fn parse_scenario(text: &str) -> Result<Scenario, String> {
+    let mut scenario = scenario_base();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let word: Vec<&str> = line.split_whitespace().collect();
+
+        match word[0] {
+            "electrons" => scenario.electrons = single(&word, n)? as u32,
+            "quarks" => scenario.quarks = single(&word, n)? as u32,
+            "spread" => {
+                scenario.spread = single(&word, n)?;
+                if !scenario.spread.is_finite() || scenario.spread <= 0.0 {
+                    return Err(format!("line {}: spread has to be positive", n + 1));
+                }
+            }
+            "field" => scenario.field.push(parse_field(&word[1..], n)?),
+            "distribution" => {
+                let text = word[1..].join(" ");
+                deserialize(&text).map_err(|e| format!("line {}: {}", n + 1, e))?;
+                scenario.distribution = text;
+            }
+            "force" => {
+                let text = word[1..].join(" ");
+                parse_force(&text).map_err(|e| format!("line {}: {}", n + 1, e))?;
+                scenario.force.push(text);
+            }
+            "wavepacket" => scenario.wavepacket = single(&word, n)?,
+            "cluster" => {
+                let regroup = word.last() == Some(&"regroup");
+                let rigid = word.last() == Some(&"rigid");
+                let v = numbers(&word[1..word.len() - (regroup || rigid) as usize], n)?;
+                if v.len() != 2 {
+                    return Err(format!("line {}: cluster takes linking and every", n + 1));
+                }
+                scenario.cluster = vec![watch_base(v[0], v[1] as u64, regroup, rigid)];
+            }
+            "sample" => {
+                let v = numbers(&word[1..], n)?;
+                if v.len() != 3 {
+                    return Err(format!(
+                        "line {}: sample takes temperature, step and every",
+                        n + 1
+                    ));
+                }
+                if !v[1].is_finite() || v[1] <= 0.0 {
+                    return Err(format!("line {}: sample step has to be positive", n + 1));
+                }
+                scenario.sampler = vec![sampler_base(v[0], v[1], v[2] as u64, 0)];
+            }
+            "nuclear" => {
+                let v = numbers(&word[1..], n)?;
+                scenario.yukawa = match v.len() {
+                    0 => vec![yukawa_base()],
+                    3 => vec![Yukawa {
+                        coupling: v[0],
+                        mass: v[1],
+                        core: v[2],
+                    }],
+                    _ => {
+                        return Err(format!(
+                            "line {}: nuclear takes coupling, pion mass and core",
+                            n + 1
+                        ))
+                    }
+                };
+            }
+            "coupling" => {
+                let name = word
+                    .get(1)
+                    .and_then(|w| property_code(w))
+                    .ok_or_else(|| format!("line {}: coupling of what", n + 1))?;
+                let v = numbers(&word[2..], n)?;
+                if v.len() != 1 {
+                    return Err(format!("line {}: coupling takes one number", n + 1));
+                }
+                scenario.coupling.push(Property { name, value: v[0] });
+            }
+            "thermostat" => {
+                let kind = word
+                    .get(1)
+                    .and_then(|w| thermostat_kind(w))
+                    .ok_or_else(|| format!("line {}: thermostat of what kind", n + 1))?;
+                let v = numbers(&word[2..], n)?;
+                if v.is_empty() || v.len() > 2 {
+                    return Err(format!(
+                        "line {}: thermostat takes a temperature and maybe tau",
+                        n + 1
+                    ));
+                }
+                // seeded from the scenario's seed when the anomaly is built
+                scenario.thermostat =
+                    vec![thermostat_base(kind, v[0], *v.get(1).unwrap_or(&0.0), 0)];
+            }
+            "radiation" => {
+                let photons = word.get(1) == Some(&"photons");
+                let v = numbers(&word[1 + photons as usize..], n)?;
+                scenario.radiation = match (photons, v.len()) {
+                    (false, 0) => vec![radiation_base(false, 0.0)],
+                    (true, 1) if v[0].is_finite() && v[0] > 0.0 => {
+                        vec![radiation_base(true, v[0])]
+                    }
+                    (true, 1) => {
+                        return Err(format!(
+                            "line {}: radiation photons need a positive threshold",
+                            n + 1
+                        ))
+                    }
+                    _ => {
+                        return Err(format!(
+                            "line {}: radiation takes photons and a threshold or nothing",
+                            n + 1
+                        ))
+                    }
+                };
+            }
+            "spawn" => {
+                let k = word
+                    .get(1)
+                    .and_then(|w| species_named(w))
+                    .ok_or_else(|| format!("line {}: spawn of what species", n + 1))?;
+                let v = numbers(&word[2..], n)?;
+                if v.len() != 1 {
+                    return Err(format!("line {}: spawn takes a species and a count", n + 1));
+                }
+                scenario.spawn.push((k, v[0] as u32));
+            }
+            "decay" => scenario.decay = vec![decay_base(0)],
+            "reactions" => scenario.reaction = vec![reaction_base(single(&word, n)?, 0)],
+            "validate" => scenario.validator = vec![validator_base()],
+            "periodic" => {
+                let v = numbers(&word[1..], n)?;
+                if v.len() < 3 || v.len() > 4 || v[..3].iter().any(|l| *l <= 0.0) {
+                    return Err(format!(
+                        "line {}: periodic takes three sides and maybe a tolerance",
+                        n + 1
+                    ));
+                }
+                scenario.periodic =
+                    vec![periodic_base([v[0], v[1], v[2]], *v.get(3).unwrap_or(&0.0))];
+            }
+            "seed" => scenario.seed = vec![single(&word, n)? as u64],
+            "ensemble" => {
+                let file = word.get(3).map_or("ensemble.csv", |w| w);
+                let v = numbers(&word[1..word.len().min(3)], n)?;
+                if v.len() != 2 {
+                    return Err(format!("line {}: ensemble takes runs and steps", n + 1));
+                }
+                scenario.ensemble = vec![ensemble_base(v[0] as u32, v[1] as u64, file)];
+            }
+            "sweep" => {
+                let named = word.get(1) == Some(&"coupling");
+                let start = if named { 3 } else { 2 };
+                if word.len() <= start {
+                    return Err(format!("line {}: sweep of what", n + 1));
+                }
+                let setting = word[1..start].join(" ");
+                let v = numbers(&word[start..], n)?;
+                if v.is_empty() {
+                    return Err(format!("line {}: sweep {} over nothing", n + 1, setting));
+                }
+                // checked on a copy, so a bad setting shows up here
+                apply_setting(&mut scenario_base(), &setting, v[0])
+                    .map_err(|e| format!("line {}: {}", n + 1, e))?;
+                scenario.sweep.push(Sweep { setting, value: v });
+            }
+            "bond" | "distance" | "angle" => scenario.constraint.push(word.join(" ")),
+            _ => return Err(format!("line {}: unknown setting {}", n + 1, word[0])),
+        }
+    }
+
+    // ties are checked once the number of particles is known, against the
+    // fewest particles any swept point leaves
+    let mut fewest = (scenario.electrons, scenario.quarks);
+    for s in &scenario.sweep {
+        let low = s.value.iter().fold(f64::MAX, |a, b| a.min(*b)) as u32;
+        match s.setting.as_str() {
+            "electrons" => fewest.0 = fewest.0.min(low),
+            "quarks" => fewest.1 = fewest.1.min(low),
+            _ => {}
+        }
+    }
+    let particles = fewest.0 + fewest.1 + spawned(&scenario);
+    check_ties(&scenario.constraint, particles)?;
+
+    return Ok(scenario);
+}
+
+fn spawned(scenario: &Scenario) -> u32 {
+    return scenario.spawn.iter().map(|(_, n)| n).sum();
+}
+
+// every tie against that many particles
+fn check_ties(constraint: &Vec<String>, particles: u32) -> Result<(), String> {
+    let particle = vec![Id::default(); particles as usize];
+    for c in constraint {
+        parse_constraint(c, &particle)?;
+    }
+    return Ok(());
+}
+
+fn numbers(word: &[&str], n: usize) -> Result<Vec<f64>, String> {
+    return word
+        .iter()
+        .map(|w| {
+            w.parse::<f64>()
+                .map_err(|_| format!("line {}: {} is not a number", n + 1, w))
+        })
+        .collect();
+}
+
+fn single(word: &[&str], n: usize) -> Result<f64, String> {
+    let v = numbers(&word[1..], n)?;
+    if v.len() != 1 {
+        return Err(format!("line {}: {} takes one number", n + 1, word[0]));
+    }
+
+    return Ok(v[0]);
+}
+
+fn parse_field(word: &[&str], n: usize) -> Result<Field, String> {
+    if word.is_empty() {
+        return Err(format!("line {}: field without a shape", n + 1));
+    }
+    let v = numbers(&word[1..], n)?;
+
+    let (mut field, count) = match word[0] {
+        "uniform" if v.len() >= 6 => (uniform([v[0], v[1], v[2]], [v[3], v[4], v[5]]), 6),
+        "solenoid" if v.len() >= 8 => (
+            solenoid([v[0], v[1], v[2]], [v[3], v[4], v[5]], v[6], v[7]),
+            8,
+        ),
+        "charge" if v.len() >= 4 => (point_charge([v[0], v[1], v[2]], v[3]), 4),
+        _ => {
+            return Err(format!(
+                "line {}: field {} with {} numbers",
+                n + 1,
+                word[0],
+                v.len()
+            ))
+        }
+    };
+
+    match v.len() - count {
+        0 => {}
+        2 => {
+            field.frequency = v[count];
+            field.phase = v[count + 1];
+        }
+        _ => return Err(format!("line {}: field takes frequency and phase", n + 1)),
+    }
+
+    return Ok(field);
+}
+
+// one value of a swept setting, as the line of the same name would set it
+pub fn apply_setting(scenario: &mut Scenario, setting: &str, value: f64) -> Result<(), String> {
+    let word: Vec<&str> = setting.split_whitespace().collect();
+    match word.as_slice() {
+        ["electrons"] => scenario.electrons = value as u32,
+        ["quarks"] => scenario.quarks = value as u32,
+        ["spread"] if value.is_finite() && value > 0.0 => scenario.spread = value,
+        ["spread"] => return Err(format!("spread {} is not positive", value)),
+        ["wavepacket"] => scenario.wavepacket = value,
+        ["coupling", name] => {
+            let name = property_code(name).ok_or_else(|| format!("unknown property {}", name))?;
+            scenario.coupling.retain(|p| p.name != name);
+            scenario.coupling.push(Property { name, value });
+        }
+        _ => return Err(format!("{} cannot be swept", setting)),
+    }
+    // fewer particles may leave a tie without its ends
+    let particles = scenario.electrons + scenario.quarks + spawned(scenario);
+    return check_ties(&scenario.constraint, particles);
+}
+
+fn set_coupling(force: &mut Vec<Force>, coupling: &Property) {
+    for f in force.iter_mut() {
+        for d in f.domain.iter_mut() {
+            for p in d.property.iter_mut().filter(|p| p.name == coupling.name) {
+                p.value = coupling.value;
+            }
+        }
+        set_coupling(&mut f.force, coupling);
+    }
+}
+
+pub fn scenario_anomaly(scenario: &Scenario) -> Result<Anomaly, String> {
+    let mut rng = match scenario.seed.first() {
+        Some(s) => StdRng::seed_from_u64(*s),
+        None => StdRng::from_entropy(),
+    };
+    // laws are compiled here once and shared by every particle
+    let force: Vec<Force> = scenario
+        .force
+        .iter()
+        .map(|f| parse_force(f))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut anomaly = Anomaly {
+        force: force_base().force,
+        field: scenario.field.clone(),
+        yukawa: scenario.yukawa.clone(),
+        thermostat: scenario
+            .thermostat
+            .iter()
+            .map(|t| thermostat_base(t.kind, t.target, t.tau, rng.gen_range(0..u64::MAX)))
+            .collect(),
+        radiation: scenario
+            .radiation
+            .iter()
+            .map(|r| radiation_base(r.photons, r.threshold))
+            .collect(),
+        decay: scenario
+            .decay
+            .iter()
+            .map(|_| decay_base(rng.gen_range(0..u64::MAX)))
+            .collect(),
+        reaction: scenario
+            .reaction
+            .iter()
+            .map(|r| reaction_base(r.reach, rng.gen_range(0..u64::MAX)))
+            .collect(),
+        periodic: scenario
+            .periodic
+            .iter()
+            .map(|p| Arc::new(ewald_base(p)))
+            .collect(),
+        ..vacuum()
+    };
+    establish(&mut anomaly);
+    let root = anomaly.id;
+
+    for _ in 0..scenario.electrons {
+        let mut p = e(
+            gen_f64_3(0.0, scenario.spread, &mut rng),
+            mltply_f64_3(nrmlz_f64_3(gen_f64_3(0.0, 10.0, &mut rng)), LS_F64),
+            true,
+        );
+        distribute(scenario, &mut p);
+        p.force.extend(force.iter().map(share_force));
+        add_particle_by(&mut anomaly, root, p);
+    }
+    for _ in 0..scenario.quarks {
+        let mut p = q(
+            gen_f64_3(0.0, scenario.spread, &mut rng),
+            mltply_f64_3(nrmlz_f64_3(gen_f64_3(0.0, 10.0, &mut rng)), LS_F64),
+            true,
+            true,
+            rng.gen_range(0..3),
+            rng.gen_range(0..1),
+        );
+        distribute(scenario, &mut p);
+        p.force.extend(force.iter().map(share_force));
+        add_particle_by(&mut anomaly, root, p);
+    }
+    for (k, count) in &scenario.spawn {
+        for _ in 0..*count {
+            let mut p = make_species(
+                *k,
+                gen_f64_3(0.0, scenario.spread, &mut rng),
+                [0.0, 0.0, 0.0],
+                0.0,
+                rng.gen_range(0..3) as f64 + if SPECIES[*k].color < 0 { 3.0 } else { 0.0 },
+                true,
+            );
+            distribute(scenario, &mut p);
+            p.force.extend(force.iter().map(share_force));
+            add_particle_by(&mut anomaly, root, p);
+        }
+    }
+
+    let particle: Vec<Id> = anomaly
+        .anomaly
+        .iter()
+        .filter_map(|a| a.component.first().map(|c| c.id))
+        .collect();
+    anomaly.constraint = scenario
+        .constraint
+        .iter()
+        .map(|c| parse_constraint(c, &particle))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    for c in &scenario.coupling {
+        visit_anomalies_mut(&mut anomaly, &mut |_, a| set_coupling(&mut a.force, c));
+    }
+
+    // what every later change is held to
+    anomaly.validator = scenario
+        .validator
+        .iter()
+        .map(|_| validator_of(&anomaly))
+        .collect();
+
+    return Ok(anomaly);
+}
+
+// the sampler of the scenario, seeded by the scenario seed like the runs of an ensemble
+pub fn scenario_sampler(scenario: &Scenario) -> Option<Sampler> {
+    let seed = match scenario.seed.first() {
+        Some(s) => *s,
+        None => StdRng::from_entropy().gen_range(0..u64::MAX),
+    };
+    return scenario
+        .sampler
+        .first()
+        .map(|s| sampler_base(s.temperature, s.step, s.every, seed));
+}
+
+fn distribute(scenario: &Scenario, p: &mut Anomaly) {
+    for c in p.component.iter_mut() {
+        if scenario.wavepacket > 0.0 {
+            make_wavepacket(scenario.wavepacket, c);
+        }
+        for k in c.composition.iter_mut() {
+            k.distribution = vec![deserialize(&scenario.distribution).unwrap()];
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::anomaly::{view, Anomaly};
+use crate::arena::Id;
+use crate::clock::{clock_base, faster, single_step, slower, steps_due, toggle_pause, Clock};
+use crate::columns::gather;
+use crate::decay::event_line;
+use crate::magma_ocean::Stone;
+use crate::reaction::collision_line;
+use crate::thermostat::temperature;
+
+// the simulation owns the anomaly on its own thread and after every batch of
+// steps publishes an immutable snapshot; the renderer holds on to the one it
+// draws and swaps in a newer one whenever the slot has it, never waiting
+
+pub struct Attribute {
+    pub id: Id,
+    pub position: [f64; 3],
+    pub inertia: [f64; 3],
+    pub mass: f64,
+    pub charge: f64,
+}
+
+pub struct Snapshot {
+    pub stone: Vec<Stone>,
+    pub attribute: Vec<Attribute>,
+    pub clock: f64,
+    pub steps: u64,
+    // steps per wall clock second
+    pub rate: f64,
+    pub paused: bool,
+    pub speed: f64,
+    // what the stones are placed relative to, planck lengths
+    pub origin: [f64; 3],
+    // instantaneous kT of the moving components, MeV
+    pub temperature: f64,
+    // what happened since the last snapshot taken, one line per event
+    pub log: Vec<String>,
+}
+
+pub enum Command {
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    // stones are placed relative to this from the next snapshot on
+    Origin([f64; 3]),
+    Stop,
+}
+
+pub struct Simulation {
+    command: mpsc::Sender<Command>,
+    latest: Arc<Mutex<Option<Arc<Snapshot>>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+pub fn simulate(
+    mut anom: Anomaly,
+    mut step: impl FnMut(&mut Anomaly) + Send + 'static,
+) -> Simulation {
+    let (tx, rx) = mpsc::channel();
+    let latest: Arc<Mutex<Option<Arc<Snapshot>>>> = Arc::new(Mutex::new(None));
+    let slot = latest.clone();
+
+    let handle = thread::spawn(move || {
+        let mut clock = clock_base();
+        let mut rate = 0.0;
+        let mut window = Instant::now();
+        let mut window_steps = 0;
+        let mut changed = true;
+        let mut origin = [0.0, 0.0, 0.0];
+
+        loop {
+            loop {
+                match rx.try_recv() {
+                    Ok(Command::Pause) => toggle_pause(&mut clock),
+                    Ok(Command::Step) => single_step(&mut clock),
+                    Ok(Command::Faster) => faster(&mut clock),
+                    Ok(Command::Slower) => slower(&mut clock),
+                    Ok(Command::Origin(o)) => origin = o,
+                    Ok(Command::Stop) | Err(TryRecvError::Disconnected) => return,
+                    Err(TryRecvError::Empty) => break,
+                }
+                changed = true;
+            }
+
+            let due = steps_due(&mut clock);
+            for _ in 0..due {
+                step(&mut anom);
+            }
+            window_steps += due;
+
+            let elapsed = window.elapsed().as_secs_f64();
+            if elapsed >= 0.5 {
+                rate = window_steps as f64 / elapsed;
+                window = Instant::now();
+                window_steps = 0;
+                changed = true;
+            }
+
+            if due > 0 || changed {
+                let mut s = snapshot(&mut anom, &clock, rate, origin);
+                let mut slot = slot.lock().unwrap();
+                // a snapshot nobody took hands its events on
+                if let Some(old) = slot.take() {
+                    s.log.splice(0..0, old.log.iter().cloned());
+                }
+                *slot = Some(Arc::new(s));
+                changed = false;
+            } else {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    });
+
+    return Simulation {
+        command: tx,
+        latest,
+        handle: Some(handle),
+    };
+}
+
+fn snapshot(anom: &mut Anomaly, clock: &Clock, rate: f64, origin: [f64; 3]) -> Snapshot {
+    let columns = gather(anom);
+    let attribute = (0..columns.len())
+        .map(|k| Attribute {
+            id: columns.id[k],
+            position: columns.position(k),
+            inertia: columns.inertia(k),
+            mass: columns.mass[k],
+            charge: columns.charge[k],
+        })
+        .collect();
+
+    return Snapshot {
+        stone: view(anom, origin),
+        attribute,
+        clock: anom.clock,
+        steps: clock.steps,
+        rate,
+        paused: clock.paused,
+        speed: clock.speed,
+        origin,
+        temperature: temperature(&columns),
+        log: events(anom),
+    };
+}
+
+// the events recorded since the last snapshot, taken out of the tree
+pub fn events(anom: &mut Anomaly) -> Vec<String> {
+    let mut ret = vec![];
+    for d in anom.decay.iter_mut() {
+        ret.extend(d.event.drain(..).map(|e| event_line(&e)));
+    }
+    for r in anom.reaction.iter_mut() {
+        ret.extend(r.collision.drain(..).map(|c| collision_line(&c)));
+    }
+    for v in anom.validator.iter_mut() {
+        ret.append(&mut v.log);
+    }
+    ret.append(&mut anom.log);
+    return ret;
+}
+
+pub fn command(sim: &Simulation, c: Command) {
+    let _ = sim.command.send(c);
+}
+
+// a snapshot newer than the last one taken, if the simulation got one out
+pub fn latest(sim: &Simulation) -> Option<Arc<Snapshot>> {
+    return match sim.latest.try_lock() {
+        Ok(mut slot) => slot.take(),
+        Err(_) => None,
+    };
+}
+
+pub fn stop(sim: &mut Simulation) {
+    command(sim, Command::Stop);
+    if let Some(h) = sim.handle.take() {
+        h.join().unwrap();
+    }
+}
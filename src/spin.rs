@@ -0,0 +1,141 @@
+use crate::anomaly::{
+    accelerate, component_position, component_property, has_component_property,
+    set_component_property, Component, EC, EP_F64, HB_F64, MS, SO0, SO1, SO2, SP,
+};
+use crate::f64_3::{
+    dd_f64_3, dot_product, mltply_f64_3, nrmlz_f64_3, rotate_f64_3, sbtr_f64_3, vector_length,
+};
+
+pub static GS_F64: f64 = 2.0;
+
+// magnetic moments are lengths in planck units, fields are MeV per planck length,
+// so that a moment in a field gives an energy in MeV
+
+pub fn spin_orientation(c: &Component) -> [f64; 3] {
+    return [
+        component_property(c, SO0),
+        component_property(c, SO1),
+        component_property(c, SO2),
+    ];
+}
+
+pub fn set_spin_orientation(so: [f64; 3], c: &mut Component) {
+    let so = nrmlz_f64_3(so);
+    set_component_property(SO0, so[0], c);
+    set_component_property(SO1, so[1], c);
+    set_component_property(SO2, so[2], c);
+}
+
+pub fn has_spin(c: &Component) -> bool {
+    return has_component_property(c, SP) && has_component_property(c, SO0);
+}
+
+// direction the spin points to, for rendering
+pub fn spin_axis(c: &Component) -> Option<[f64; 3]> {
+    if !has_spin(c) {
+        return None;
+    }
+
+    return Some(nrmlz_f64_3(mltply_f64_3(
+        spin_orientation(c),
+        component_property(c, SP).signum(),
+    )));
+}
+
+// moment per unit of spin orientation, g * q / 2m in planck lengths
+pub fn gyromagnetic(c: &Component) -> f64 {
+    if !has_component_property(c, EC) || !has_component_property(c, MS) {
+        return 0.0;
+    }
+    let mass = component_property(c, MS);
+    if mass <= 0.0 {
+        return 0.0;
+    }
+
+    return GS_F64 * component_property(c, EC) * EP_F64 / (2.0 * mass);
+}
+
+pub fn magnetic_moment(c: &Component) -> [f64; 3] {
+    if !has_spin(c) {
+        return [0.0, 0.0, 0.0];
+    }
+
+    return mltply_f64_3(
+        spin_orientation(c),
+        gyromagnetic(c) * component_property(c, SP),
+    );
+}
+
+// field of a dipole at displacement r from it
+pub fn dipole_field(k: f64, mu: [f64; 3], r: [f64; 3]) -> [f64; 3] {
+    let d = vector_length(r);
+    if d == 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+    let n = mltply_f64_3(r, 1.0 / d);
+
+    return mltply_f64_3(
+        sbtr_f64_3(mltply_f64_3(n, 3.0 * dot_product(mu, n)), mu),
+        k / d.powi(3),
+    );
+}
+
+// force on dipole two, r pointing from dipole one to dipole two
+pub fn dipole_force(k: f64, mu1: [f64; 3], mu2: [f64; 3], r: [f64; 3]) -> [f64; 3] {
+    let d = vector_length(r);
+    if d == 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+    let n = mltply_f64_3(r, 1.0 / d);
+    let m1n = dot_product(mu1, n);
+    let m2n = dot_product(mu2, n);
+
+    let mut f = mltply_f64_3(mu2, m1n);
+    f = dd_f64_3(f, mltply_f64_3(mu1, m2n));
+    f = dd_f64_3(f, mltply_f64_3(n, dot_product(mu1, mu2)));
+    f = sbtr_f64_3(f, mltply_f64_3(n, 5.0 * m1n * m2n));
+
+    return mltply_f64_3(f, 3.0 * k / d.powi(4));
+}
+
+// torque mu x B turns the spin around the field at the larmor rate
+pub fn spin_precess(b: [f64; 3], time: f64, c: &mut Component) {
+    if !has_spin(c) {
+        return;
+    }
+    let omega = mltply_f64_3(b, -gyromagnetic(c) / HB_F64);
+    let rate = vector_length(omega);
+    if rate == 0.0 {
+        return;
+    }
+
+    let so = rotate_f64_3(
+        spin_orientation(c),
+        mltply_f64_3(omega, 1.0 / rate),
+        rate * time,
+    );
+    set_spin_orientation(so, c);
+}
+
+pub fn spin_interact(coupling: f64, a: &mut Component, b: &mut Component, time: f64) {
+    if !has_spin(a) || !has_spin(b) {
+        return;
+    }
+
+    let r = sbtr_f64_3(component_position(b), component_position(a));
+    let d = vector_length(r);
+    if d == 0.0 {
+        return;
+    }
+
+    let k = coupling * EP_F64;
+    let mu_a = magnetic_moment(a);
+    let mu_b = magnetic_moment(b);
+
+    let f = dipole_force(k, mu_a, mu_b, r);
+    accelerate(mltply_f64_3(f, -1.0), time, a);
+    accelerate(f, time, b);
+
+    spin_precess(dipole_field(k, mu_b, mltply_f64_3(r, -1.0)), time, a);
+    spin_precess(dipole_field(k, mu_a, r), time, b);
+}
@@ -0,0 +1,186 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::anomaly::{LS_F64, TS_F64};
+use crate::columns::Columns;
+
+// keeps the columns near a temperature by working on their velocities around
+// the center of mass after each step:
+//
+// rescale     - straight onto the target every step
+// berendsen   - towards the target with time constant tau
+// langevin    - friction 1 / tau and the matching random kicks
+// nose-hoover - a friction variable driven by the difference, period tau
+//
+// temperatures are kT in MeV, tau in seconds; the instantaneous temperature is
+// twice the kinetic energy around the center of mass over 3N - 3, N counting
+// the massive columns; photons are left going at c
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Rescale,
+    Berendsen,
+    Langevin,
+    NoseHoover,
+}
+
+pub struct Thermostat {
+    pub kind: Kind,
+    pub target: f64,
+    pub tau: f64,
+    // the temperature the last step left, MeV
+    pub measured: f64,
+    // nose-hoover friction, 1 / s
+    xi: f64,
+    rng: StdRng,
+}
+
+pub fn thermostat_base(kind: Kind, target: f64, tau: f64, seed: u64) -> Thermostat {
+    return Thermostat {
+        kind,
+        target,
+        tau: if tau > 0.0 { tau } else { 100.0 * TS_F64 },
+        measured: 0.0,
+        xi: 0.0,
+        rng: StdRng::seed_from_u64(seed),
+    };
+}
+
+pub fn thermostat_kind(name: &str) -> Option<Kind> {
+    return match name {
+        "rescale" => Some(Kind::Rescale),
+        "berendsen" => Some(Kind::Berendsen),
+        "langevin" => Some(Kind::Langevin),
+        "nose-hoover" => Some(Kind::NoseHoover),
+        _ => None,
+    };
+}
+
+fn drift(columns: &Columns) -> [f64; 3] {
+    let mass: f64 = columns.mass.iter().sum();
+    if mass <= 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+    let mut v = [0.0, 0.0, 0.0];
+    for k in 0..columns.len() {
+        let i = columns.inertia(k);
+        for x in 0..3 {
+            v[x] += columns.mass[k] * i[x] / mass;
+        }
+    }
+    return v;
+}
+
+// kT in MeV, of the massive columns only
+pub fn temperature(columns: &Columns) -> f64 {
+    let n = columns.mass.iter().filter(|m| **m > 0.0).count();
+    if n < 2 {
+        return 0.0;
+    }
+    let v = drift(columns);
+    let mut kinetic = 0.0;
+    for k in 0..columns.len() {
+        if columns.mass[k] <= 0.0 {
+            continue;
+        }
+        let i = columns.inertia(k);
+        let u2 = (i[0] - v[0]).powi(2) + (i[1] - v[1]).powi(2) + (i[2] - v[2]).powi(2);
+        kinetic += 0.5 * columns.mass[k] * u2 / (LS_F64 * LS_F64);
+    }
+    return 2.0 * kinetic / (3.0 * n as f64 - 3.0);
+}
+
+// velocities around the drift scaled by s, photons keep going at c
+fn scale(columns: &mut Columns, s: f64) {
+    let v = drift(columns);
+    for k in 0..columns.len() {
+        if columns.mass[k] <= 0.0 {
+            continue;
+        }
+        columns.ix[k] = v[0] + (columns.ix[k] - v[0]) * s;
+        columns.iy[k] = v[1] + (columns.iy[k] - v[1]) * s;
+        columns.iz[k] = v[2] + (columns.iz[k] - v[2]) * s;
+    }
+}
+
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u: f64 = rng.gen_range(f64::MIN_POSITIVE..1.0);
+    let w: f64 = rng.gen_range(0.0..1.0);
+    return (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * w).cos();
+}
+
+pub fn thermostat_step(t: &mut Thermostat, columns: &mut Columns, time: f64) {
+    let now = temperature(columns);
+
+    match t.kind {
+        Kind::Rescale if now > 0.0 => scale(columns, (t.target / now).sqrt()),
+        Kind::Berendsen if now > 0.0 => {
+            let s = 1.0 + time / t.tau * (t.target / now - 1.0);
+            scale(columns, s.max(0.0).sqrt());
+        }
+        Kind::Langevin => {
+            let keep = (-time / t.tau).exp();
+            let noise = (1.0 - keep * keep).sqrt();
+            for k in 0..columns.len() {
+                if columns.mass[k] <= 0.0 {
+                    continue;
+                }
+                let sigma = LS_F64 * (t.target / columns.mass[k]).sqrt() * noise;
+                columns.ix[k] = columns.ix[k] * keep + sigma * gaussian(&mut t.rng);
+                columns.iy[k] = columns.iy[k] * keep + sigma * gaussian(&mut t.rng);
+                columns.iz[k] = columns.iz[k] * keep + sigma * gaussian(&mut t.rng);
+            }
+        }
+        Kind::NoseHoover if t.target > 0.0 => {
+            t.xi += time * (now / t.target - 1.0) / (t.tau * t.tau);
+            scale(columns, (-t.xi * time).exp());
+        }
+        _ => {}
+    }
+
+    for k in 0..columns.len() {
+        let v2 = columns.ix[k].powi(2) + columns.iy[k].powi(2) + columns.iz[k].powi(2);
+        if v2 > LS_F64 * LS_F64 {
+            let s = LS_F64 / v2.sqrt();
+            columns.ix[k] *= s;
+            columns.iy[k] *= s;
+            columns.iz[k] *= s;
+        }
+    }
+    t.measured = temperature(columns);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::{add_particle_by, e, photon, vacuum};
+    use crate::arena::establish;
+    use crate::columns::gather;
+
+    #[test]
+    fn photons_keep_the_speed_of_light() {
+        let mut root = vacuum();
+        establish(&mut root);
+        let top = root.id;
+        add_particle_by(&mut root, top, e([0.0; 3], [0.1 * LS_F64, 0.0, 0.0], true));
+        add_particle_by(
+            &mut root,
+            top,
+            e([1e20, 0.0, 0.0], [0.0, 0.2 * LS_F64, 0.0], true),
+        );
+        add_particle_by(
+            &mut root,
+            top,
+            photon([0.0, 1e20, 0.0], [0.0, 0.0, LS_F64], 1.0),
+        );
+        let mut columns = gather(&root);
+        assert_eq!(columns.len(), 3);
+
+        let before = temperature(&columns);
+        let mut t = thermostat_base(Kind::Rescale, 0.25 * before, 0.0, 0);
+        thermostat_step(&mut t, &mut columns, TS_F64);
+        let k = columns.mass.iter().position(|m| *m == 0.0).unwrap();
+        assert_eq!(columns.inertia(k), [0.0, 0.0, LS_F64]);
+        assert!((t.measured - 0.25 * before).abs() <= 1e-9 * before);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::anomaly::{
+    component_property, has_component_property, Anomaly, Component, Property, HB_F64, LS_F64, MS,
+    WA, WD,
+};
+use crate::distribution::gaussian_draws;
+use crate::f32_3::{dd_f32_3, mltply_f32_3};
+use crate::magma_ocean::{magma, petrify, Stone};
+use crate::positions::move_positions;
+use crate::query::visit_components_mut;
+
+// a component can be a free gaussian wavepacket instead of a point: its space
+// holds where the packet is expected, WD the width it was prepared with and WA
+// how long ago that was; the width grows as a free packet's does
+//
+//   sigma(t) = sigma0 * sqrt(1 + (hbar c^2 t / (2 m sigma0^2))^2)
+//
+// forces see the expectation of the pair force between two such clouds
+
+pub static WAVE_SAMPLES: u32 = 24;
+
+pub fn make_wavepacket(sigma: f64, c: &mut Component) {
+    c.property.push(Property {
+        name: WD,
+        value: sigma,
+    });
+    c.property.push(Property {
+        name: WA,
+        value: 0.0,
+    });
+}
+
+// the width now in planck lengths, None for points and anything without a mass
+pub fn wavepacket_width(c: &Component) -> Option<f64> {
+    if ![WD, WA, MS].iter().all(|p| has_component_property(c, *p)) {
+        return None;
+    }
+    let sigma = component_property(c, WD);
+    let age = component_property(c, WA);
+    let mass = component_property(c, MS);
+    if sigma <= 0.0 || mass <= 0.0 {
+        return Some(sigma.max(0.0));
+    }
+
+    let tau = HB_F64 * LS_F64 * LS_F64 * age / (2.0 * mass * sigma * sigma);
+    return Some(sigma * (1.0 + tau * tau).sqrt());
+}
+
+pub fn age_wavepackets(anom: &mut Anomaly, time: f64) {
+    visit_components_mut(anom, &mut |_, c| {
+        for p in c.property.iter_mut() {
+            if p.name == WA {
+                p.value += time;
+            }
+        }
+    });
+}
+
+// abramowitz and stegun 7.1.26, good to about 1e-7
+pub fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let y = 1.0
+        - (((((1.061405429 * t - 1.453152027) * t) + 1.421413741) * t - 0.284496736) * t
+            + 0.254829592)
+            * t
+            * (-x * x).exp();
+    return if x < 0.0 { -y } else { y };
+}
+
+// how much of the point force between two gaussian clouds is left at distance d,
+// s being the two widths added in quadrature
+pub fn smeared(d: f64, s: f64) -> f64 {
+    if s <= 0.0 {
+        return 1.0;
+    }
+    let x = d / (std::f64::consts::SQRT_2 * s);
+    return erf(x) - 2.0 / std::f64::consts::PI.sqrt() * x * (-x * x).exp();
+}
+
+// a fixed cloud of small stones, scaled to the width of the moment
+pub fn wavepacket_view(c: &Component, center: [f32; 3], size: f32) -> Vec<Stone> {
+    let sigma = wavepacket_width(c).unwrap_or(0.0) as f32;
+    let seed = c.id.index as u64;
+
+    let mut ret = vec![];
+    for d in gaussian_draws(WAVE_SAMPLES, seed) {
+        let mut s = petrify(magma(2, size / 4.0));
+        move_positions(&mut s.positions, dd_f32_3(center, mltply_f32_3(d, sigma)));
+        ret.push(s);
+    }
+    return ret;
+}